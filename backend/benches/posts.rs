@@ -0,0 +1,51 @@
+//! Listing and search throughput over a generated catalogue. Needs the loadtest
+//! feature: `cargo bench --features loadtest`. For HTTP level numbers run the
+//! server with the same feature and point oha at `/posts?q=...` instead.
+use axum::{
+    Router,
+    body::{Body, to_bytes},
+    http::Request,
+};
+use criterion::{Criterion, criterion_group, criterion_main};
+use pallet_spaces::{
+    appstate::AppState, create_router, initialise_tables, model::database::Database,
+    plugins::posts::Post, urls::UrlBuilder,
+};
+use tokio::runtime::Runtime;
+use tower::ServiceExt;
+
+const POSTS: u32 = 5_000;
+
+async fn seeded_router() -> Router {
+    let file = std::env::temp_dir().join(format!("pallet-spaces-bench-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&file);
+    let db = Database::open(file.to_str().unwrap()).await.unwrap();
+    let db = initialise_tables(db).await.unwrap();
+    Post::generate_posts(POSTS, &db).await.unwrap();
+    create_router(AppState::new(
+        db,
+        UrlBuilder::new("http://localhost"),
+        false,
+    ))
+}
+
+async fn get(router: &Router, path: &str) {
+    let request = Request::get(path).body(Body::empty()).unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    to_bytes(response.into_body(), usize::MAX).await.unwrap();
+}
+
+fn posts(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let router = runtime.block_on(seeded_router());
+    c.bench_function("list posts", |b| {
+        b.to_async(&runtime).iter(|| get(&router, "/posts"))
+    });
+    c.bench_function("search posts", |b| {
+        b.to_async(&runtime)
+            .iter(|| get(&router, "/posts?q=12%20pallets"))
+    });
+}
+
+criterion_group!(benches, posts);
+criterion_main!(benches);
@@ -1,12 +1,19 @@
-use crate::model::database::Database;
+use crate::{model::database::Database, urls::UrlBuilder};
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: Database,
+    pub urls: UrlBuilder,
+    /// Closed beta mode, signups need a valid invite code.
+    pub invite_required: bool,
 }
 
 impl AppState {
-    pub fn new(pool: Database) -> Self {
-        AppState { pool: pool }
+    pub fn new(pool: Database, urls: UrlBuilder, invite_required: bool) -> Self {
+        AppState {
+            pool,
+            urls,
+            invite_required,
+        }
     }
 }
@@ -0,0 +1,140 @@
+//! Post-deploy smoke test, run as `smoke [base url]` (defaults to the local server).
+//!
+//! Signs up a throwaway user, logs in and checks the key pages respond. Exits
+//! non-zero on the first failure so it can gate a deploy. Set `SMOKE_INVITE` when
+//! the target has `INVITE_REQUIRED=true`.
+
+use std::{
+    process::ExitCode,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use pallet_spaces::routes;
+use reqwest::{Client, StatusCode};
+
+const DEFAULT_BASE_URL: &str = "http://127.0.0.1:37373";
+const PUBLIC_PAGES: [&str; 6] = [
+    routes::HOME,
+    routes::POSTS,
+    routes::SIGNUP,
+    routes::LOGIN,
+    routes::SUPPORT,
+    routes::STATUS,
+];
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let base_url = std::env::args()
+        .nth(1)
+        .unwrap_or(DEFAULT_BASE_URL.to_string());
+    let base_url = base_url.trim_end_matches('/');
+    match run(base_url).await {
+        Ok(_) => {
+            println!("Smoke test passed against {}", base_url);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("Smoke test failed against {}: {}", base_url, err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(base_url: &str) -> Result<(), String> {
+    let client = Client::builder()
+        .cookie_store(true)
+        .build()
+        .map_err(|err| format!("Couldn't build http client: {}", err))?;
+
+    for page in PUBLIC_PAGES {
+        expect_status(&client, base_url, page, StatusCode::OK).await?;
+    }
+    let health: serde_json::Value = client
+        .get(format!("{}{}", base_url, routes::STATUS))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| format!("{}: {}", routes::STATUS, err))?
+        .json()
+        .await
+        .map_err(|err| format!("{} isn't json: {}", routes::STATUS, err))?;
+    if health["status"] != "ok" {
+        return Err(format!("{} reports {}", routes::STATUS, health));
+    }
+    println!("ok   {} reports healthy", routes::STATUS);
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let email = format!("smoke-{}@example.com", now);
+    let password = format!("smoke-{}", uuid::Uuid::new_v4().simple());
+    // Backdated so the signup form's minimum fill time check lets us through
+    let rendered_at = (now - 10).to_string();
+    let invite_code = std::env::var("SMOKE_INVITE").unwrap_or_default();
+    let signup = client
+        .post(format!("{}{}", base_url, routes::SIGNUP))
+        .form(&[
+            ("name", "Smoke Test"),
+            ("email", email.as_str()),
+            ("password", password.as_str()),
+            ("invite_code", invite_code.as_str()),
+            ("website", ""),
+            ("form_rendered_at", rendered_at.as_str()),
+        ])
+        .send()
+        .await
+        .map_err(|err| format!("{}: {}", routes::SIGNUP, err))?;
+    if signup.status() != StatusCode::OK {
+        return Err(format!("{} returned {}", routes::SIGNUP, signup.status()));
+    }
+    println!("ok   signed up {}", email);
+
+    let login = client
+        .post(format!("{}{}", base_url, routes::LOGIN))
+        .form(&[
+            ("email", email.as_str()),
+            ("password", password.as_str()),
+            ("next", routes::NEW_POST),
+        ])
+        .send()
+        .await
+        .map_err(|err| format!("{}: {}", routes::LOGIN, err))?;
+    if login.status() != StatusCode::OK || login.url().path() != routes::NEW_POST {
+        return Err(format!(
+            "{} ended at {} with {}, expected {}",
+            routes::LOGIN,
+            login.url().path(),
+            login.status(),
+            routes::NEW_POST
+        ));
+    }
+    println!("ok   logged in and reached {}", routes::NEW_POST);
+
+    expect_status(&client, base_url, "/no-such-page", StatusCode::NOT_FOUND).await
+}
+
+async fn expect_status(
+    client: &Client,
+    base_url: &str,
+    path: &str,
+    expected: StatusCode,
+) -> Result<(), String> {
+    let response = client
+        .get(format!("{}{}", base_url, path))
+        .send()
+        .await
+        .map_err(|err| format!("{}: {}", path, err))?;
+    match response.status() == expected {
+        true => {
+            println!("ok   {} {}", path, expected);
+            Ok(())
+        }
+        false => Err(format!(
+            "{} returned {}, expected {}",
+            path,
+            response.status(),
+            expected
+        )),
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{
+    error::Error,
+    model::database::{Database, DatabaseComponent},
+    plugins::{invites::Invite, users::User},
+};
+
+/// One-off admin commands, run as `backend <command> [args]` instead of serving.
+pub async fn run_command(command: &str, args: &[String], db: &Database) -> Result<(), Error> {
+    match command {
+        "create-invite" => {
+            let max_uses = match args.first() {
+                Some(raw) => match raw.parse() {
+                    Ok(max_uses) if max_uses > 0 => max_uses,
+                    _ => {
+                        return Err(Error::Config(format!(
+                            "Invalid max uses {:?}, expected a whole number above 0",
+                            raw
+                        )));
+                    }
+                },
+                None => 1,
+            };
+            let invite = Invite::new(max_uses);
+            let code = invite.code.clone();
+            db.create(invite).await?;
+            println!("Created invite {} ({} uses)", code, max_uses);
+            Ok(())
+        }
+        "suspend-user" | "unsuspend-user" => {
+            let email = args
+                .first()
+                .ok_or(Error::Config(format!("{} needs an email", command)))?;
+            let suspend = command == "suspend-user";
+            User::set_suspended(email, suspend, db).await?;
+            println!(
+                "{} {}",
+                if suspend { "Suspended" } else { "Reinstated" },
+                email
+            );
+            Ok(())
+        }
+        _ => Err(Error::Config(format!(
+            "Unknown command {:?}, expected create-invite [max uses], suspend-user <email> or unsuspend-user <email>",
+            command
+        ))),
+    }
+}
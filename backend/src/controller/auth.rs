@@ -0,0 +1,83 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{StatusCode, request::Parts},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_login::AuthSession;
+
+use crate::{model::database::Database, plugins::users::User, routes};
+
+/// Extractor for routes that need a logged in user. Anyone else is sent to
+/// `/login?next=<original url>`, htmx requests get a 401 instead which
+/// `default_header` turns into the same redirect for the page they're on.
+pub struct RequireUser(pub User);
+
+impl<S: Send + Sync> FromRequestParts<S> for RequireUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let auth_session = AuthSession::<Database>::from_request_parts(parts, state)
+            .await
+            .map_err(|rejection| rejection.into_response())?;
+        match auth_session.user {
+            Some(user) => Ok(RequireUser(user)),
+            None if parts.headers.contains_key("hx-request") => {
+                Err(StatusCode::UNAUTHORIZED.into_response())
+            }
+            None => {
+                let original = match parts.uri.path_and_query() {
+                    Some(path_and_query) => path_and_query.as_str(),
+                    None => routes::HOME,
+                };
+                Err(Redirect::to(&routes::login(original)).into_response())
+            }
+        }
+    }
+}
+
+/// Only follow `next` to a path on this site, anything else could bounce a freshly
+/// logged in user to someone else's page.
+pub fn safe_next(next: &str) -> &str {
+    let local_path = next.starts_with('/') && !next.starts_with("//") && !next.starts_with("/\\");
+    match local_path && !next.chars().any(|c| c.is_control()) {
+        true => next,
+        false => routes::HOME,
+    }
+}
+
+pub fn percent_encode(raw: &str) -> String {
+    raw.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::safe_next;
+
+    #[test]
+    fn keeps_local_paths_with_query() {
+        assert_eq!(safe_next("/posts?q=dock"), "/posts?q=dock");
+        assert_eq!(safe_next("/new_post"), "/new_post");
+    }
+
+    #[test]
+    fn rejects_open_redirects() {
+        for next in [
+            "",
+            "https://evil.example",
+            "//evil.example",
+            "/\\evil.example",
+            "javascript:alert(1)",
+            "evil.example/posts",
+            "/\r\nLocation: https://evil.example",
+        ] {
+            assert_eq!(safe_next(next), "/", "{:?} should not be followed", next);
+        }
+    }
+}
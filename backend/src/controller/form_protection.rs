@@ -0,0 +1,44 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Humans take at least this long to fill in a form, bots usually don't.
+const MIN_FILL_TIME: Duration = Duration::from_secs(3);
+
+/// Hidden fields rendered by `views::utils::form_protection_fields`, flatten this
+/// into a form payload and call [`FormProtection::is_probably_bot`] before doing any work.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct FormProtection {
+    /// Honeypot, hidden from people so it should always come back empty.
+    #[serde(default)]
+    pub website: String,
+    /// Unix time in seconds when the form was rendered.
+    #[serde(default)]
+    pub form_rendered_at: String,
+}
+
+impl FormProtection {
+    pub fn is_probably_bot(&self) -> bool {
+        if !self.website.is_empty() {
+            return true;
+        }
+        let rendered_at = match self.form_rendered_at.parse::<u64>() {
+            Ok(secs) => UNIX_EPOCH.checked_add(Duration::from_secs(secs)),
+            Err(_) => None,
+        };
+        let Some(rendered_at) = rendered_at else {
+            return true;
+        };
+        match SystemTime::now().duration_since(rendered_at) {
+            Ok(elapsed) => elapsed < MIN_FILL_TIME,
+            Err(_) => true,
+        }
+    }
+}
+
+pub fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
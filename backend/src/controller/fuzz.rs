@@ -0,0 +1,128 @@
+//! Property tests feeding arbitrary input through the same extractors the handlers
+//! use, malformed requests should be rejected, never panic.
+use axum::{
+    body::Body,
+    extract::{Form, FromRequest, Query},
+    http::{Request, Uri, header},
+};
+use proptest::prelude::*;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    controller::{auth::safe_next, form_protection::FormProtection},
+    plugins::{
+        posts::{NewPost, PostsFilter},
+        tickets::NewTicket,
+        users::{Credential, LoginParams, SignupUser},
+    },
+    routes,
+};
+
+fn parse_form<T: DeserializeOwned>(body: &str) -> Option<T> {
+    let request = Request::post("/")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(body.to_owned()))
+        .unwrap();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime
+        .block_on(Form::<T>::from_request(request, &()))
+        .ok()
+        .map(|Form(payload)| payload)
+}
+
+fn parse_query<T: DeserializeOwned>(query: &str) -> Option<T> {
+    let uri: Uri = format!("/?{}", query).parse().ok()?;
+    Query::<T>::try_from_uri(&uri)
+        .ok()
+        .map(|Query(params)| params)
+}
+
+/// Url encoded looking input, mixing known field names with junk.
+fn form_body() -> impl Strategy<Value = String> {
+    let key = prop_oneof![
+        Just("name".to_string()),
+        Just("email".to_string()),
+        Just("password".to_string()),
+        Just("notes".to_string()),
+        Just("category".to_string()),
+        Just("message".to_string()),
+        Just("next".to_string()),
+        Just("q".to_string()),
+        Just("website".to_string()),
+        Just("form_rendered_at".to_string()),
+        "[a-z_%\\[\\]]{0,12}",
+    ];
+    let value = prop_oneof![
+        "\\PC{0,24}",
+        "%[0-9A-Fa-f]{0,2}",
+        any::<u64>().prop_map(|n| n.to_string()),
+        "-?[0-9]{1,40}",
+    ];
+    prop::collection::vec((key, value), 0..8).prop_map(|pairs| {
+        pairs
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("&")
+    })
+}
+
+proptest! {
+    #[test]
+    fn signup_form_never_panics(body in form_body()) {
+        if let Some(signup) = parse_form::<SignupUser>(&body) {
+            signup.protection.is_probably_bot();
+        }
+    }
+
+    #[test]
+    fn ticket_form_never_panics(body in form_body()) {
+        if let Some(ticket) = parse_form::<NewTicket>(&body) {
+            ticket.is_valid();
+            ticket.protection.is_probably_bot();
+        }
+    }
+
+    #[test]
+    fn login_and_post_forms_never_panic(body in form_body()) {
+        if let Some(credential) = parse_form::<Credential>(&body) {
+            prop_assert!(safe_next(&credential.next).starts_with('/'));
+        }
+        parse_form::<NewPost>(&body);
+    }
+
+    #[test]
+    fn raw_form_bytes_never_panic(body in "\\PC{0,64}") {
+        parse_form::<SignupUser>(&body);
+        parse_form::<NewTicket>(&body);
+        parse_form::<Credential>(&body);
+    }
+
+    #[test]
+    fn queries_never_panic(query in form_body()) {
+        parse_query::<PostsFilter>(&query);
+        if let Some(params) = parse_query::<LoginParams>(&query) {
+            let next = safe_next(params.next.as_deref().unwrap_or_default());
+            prop_assert!(next.starts_with('/'));
+            routes::login(next);
+        }
+    }
+
+    #[test]
+    fn any_render_time_is_handled(website in "\\PC{0,4}", rendered_at in "\\PC{0,24}|[0-9]{1,24}") {
+        FormProtection { website, form_rendered_at: rendered_at }.is_probably_bot();
+    }
+
+    #[test]
+    fn far_future_render_time_is_a_bot(secs in any::<u64>()) {
+        let protection = FormProtection {
+            website: String::new(),
+            form_rendered_at: secs.to_string(),
+        };
+        if secs > crate::controller::form_protection::now_timestamp() {
+            prop_assert!(protection.is_probably_bot());
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::any::Any;
+
+use axum::{
+    Json,
+    extract::Request,
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_login::{AuthSession, AuthUser};
+use tracing::Span;
+
+use crate::{
+    model::database::Database,
+    reporting::{self, ErrorReport},
+    routes,
+    views::{context::ViewContext, utils::server_error_page},
+};
+
+/// Paths served verbatim, e.g. static files whose names are case sensitive.
+const CASE_SENSITIVE_PREFIXES: &[&str] = &[routes::PUBLIC];
+
+/// Marks a response as coming from a caught panic, see [`render_panic_response`].
+/// Holds the panic message.
+#[derive(Clone)]
+struct Panicked(String);
+
+/// Root span for every request, `user_id` is filled in by [`record_current_user`]
+/// once the session has been resolved.
+pub fn make_request_span(request: &Request) -> Span {
+    let request_id = request_id(request);
+    tracing::info_span!(
+        "http.request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id = %request_id,
+        user_id = tracing::field::Empty,
+    )
+}
+
+/// The `http.request` span. The session layer enters its own span, so by the time
+/// the user is known `Span::current()` is no longer the request span.
+#[derive(Clone)]
+pub struct RequestSpan(Span);
+
+/// Runs just inside the trace layer to keep hold of the request span for
+/// [`record_current_user`].
+pub async fn stash_request_span(mut request: Request, next: Next) -> Response {
+    request
+        .extensions_mut()
+        .insert(RequestSpan(Span::current()));
+    next.run(request).await
+}
+
+pub async fn record_current_user(
+    auth_session: AuthSession<Database>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let (Some(user), Some(RequestSpan(span))) = (
+        &auth_session.user,
+        request.extensions().get::<RequestSpan>(),
+    ) {
+        span.record("user_id", user.id());
+    }
+    next.run(request).await
+}
+
+/// Redirects `/Posts/` style paths to their canonical lowercase form without a
+/// trailing slash, so external links never 404 over slash or case differences.
+pub async fn canonical_path_redirect(request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    if CASE_SENSITIVE_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+    {
+        return next.run(request).await;
+    }
+    // Leading slashes collapse too, `//evil.example` in a Location header is another site.
+    // Browsers treat a backslash like a slash there.
+    let trimmed = path.trim_start_matches(['/', '\\']).trim_end_matches('/');
+    let canonical = format!("/{}", trimmed.to_lowercase());
+    if canonical == path {
+        return next.run(request).await;
+    }
+    let target = match request.uri().query() {
+        Some(query) => format!("{}?{}", canonical, query),
+        None => canonical,
+    };
+    Redirect::permanent(&target).into_response()
+}
+
+/// Handler for `CatchPanicLayer`. It only sees the panic payload, so it just logs it
+/// and marks the response for [`render_panic_response`] to fill in.
+pub fn handle_panic(err: Box<dyn Any + Send + 'static>) -> Response {
+    let detail = match (err.downcast_ref::<String>(), err.downcast_ref::<&str>()) {
+        (Some(message), _) => message.to_string(),
+        (None, Some(message)) => message.to_string(),
+        (None, None) => "unknown panic".to_string(),
+    };
+    tracing::error!("Handler panicked: {}", detail);
+    let mut response = StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    response.extensions_mut().insert(Panicked(detail));
+    response
+}
+
+/// Swaps the bare panic response for the branded error page, or JSON under `/api`,
+/// including the request id so support can find the logs. The panic is also sent
+/// to `ERROR_WEBHOOK_URL` when that's set.
+pub async fn render_panic_response(request: Request, next: Next) -> Response {
+    let request_id = request_id(&request).to_string();
+    let path = request.uri().path().to_string();
+    let is_api = path.starts_with(routes::API);
+    let response = next.run(request).await;
+    let Some(Panicked(detail)) = response.extensions().get::<Panicked>() else {
+        return response;
+    };
+    reporting::report(ErrorReport::new(&request_id, &path, detail));
+    let status = StatusCode::INTERNAL_SERVER_ERROR;
+    match is_api {
+        true => (
+            status,
+            Json(serde_json::json!({
+                "error": "internal server error",
+                "request_id": request_id,
+            })),
+        )
+            .into_response(),
+        false => (
+            status,
+            server_error_page(&ViewContext::anonymous(), &request_id),
+        )
+            .into_response(),
+    }
+}
+
+fn request_id(request: &Request) -> &str {
+    request
+        .headers()
+        .get("x-request-id")
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::{Request, StatusCode},
+        middleware::from_fn,
+        routing::get,
+    };
+    use tower::ServiceExt;
+    use tower_http::catch_panic::CatchPanicLayer;
+
+    use super::{handle_panic, render_panic_response};
+
+    async fn panicking() -> &'static str {
+        panic!("boom")
+    }
+
+    async fn call(path: &str) -> (StatusCode, String) {
+        let router = Router::new()
+            .route("/boom", get(panicking))
+            .route("/api/boom", get(panicking))
+            .layer(CatchPanicLayer::custom(handle_panic))
+            .layer(from_fn(render_panic_response));
+        let request = Request::get(path)
+            .header("x-request-id", "req-1234")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    #[tokio::test]
+    async fn panic_renders_error_page() {
+        let (status, body) = call("/boom").await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(body.contains("500: Something went wrong"));
+        assert!(body.contains("req-1234"));
+    }
+
+    #[tokio::test]
+    async fn panic_under_api_is_json() {
+        let (status, body) = call("/api/boom").await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["error"], "internal server error");
+        assert_eq!(json["request_id"], "req-1234");
+    }
+}
@@ -1,6 +1,21 @@
-use axum::Router;
+use axum::{
+    Json, Router,
+    http::{StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
 
-use crate::appstate::AppState;
+pub mod auth;
+pub mod form_protection;
+#[cfg(test)]
+mod fuzz;
+pub mod middleware;
+pub mod status;
+
+use crate::{
+    appstate::AppState,
+    routes,
+    views::{context::ViewContext, utils::page_not_found},
+};
 pub trait Routes {
     fn add_routes<T: RouteProvider>(self) -> Self;
 }
@@ -14,3 +29,15 @@ impl Routes for Router<AppState> {
         T::provide_routes(self)
     }
 }
+
+/// Fallback for paths no plugin routes, JSON for `/api` and the 404 page otherwise.
+pub async fn fallback(context: ViewContext, uri: Uri) -> Response {
+    match uri.path().starts_with(routes::API) {
+        true => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "not found", "path": uri.path() })),
+        )
+            .into_response(),
+        false => (StatusCode::NOT_FOUND, page_not_found(&context)).into_response(),
+    }
+}
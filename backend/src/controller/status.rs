@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+use axum::{Json, extract::State};
+use serde_json::{Value, json};
+use sqlx::{Pool, Sqlite};
+
+use crate::appstate::AppState;
+
+/// How long to wait for a pooled connection before calling the pool degraded, well
+/// under a status page's own request timeout.
+const ACQUIRE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Component health summary for an external status page. Add new components
+/// (payments, job queue, ...) here as they land.
+pub async fn status_json(State(state): State<AppState>) -> Json<Value> {
+    let reader = component_status(state.pool.reader()).await;
+    let writer = component_status(state.pool.writer()).await;
+    let healthy = reader["status"] == "ok" && writer["status"] == "ok";
+    Json(json!({
+        "status": if healthy { "ok" } else { "degraded" },
+        "version": env!("CARGO_PKG_VERSION"),
+        "components": {
+            "database_read": reader,
+            "database_write": writer,
+        },
+    }))
+}
+
+/// Pings a pool, reporting how long acquiring a connection took alongside the
+/// pool gauges so saturation shows up before queries start failing. A pool that
+/// can't hand out a connection within [`ACQUIRE_TIMEOUT`] is degraded rather than
+/// holding the status request open.
+async fn component_status(pool: &Pool<Sqlite>) -> Value {
+    let started = Instant::now();
+    let connection = tokio::time::timeout(ACQUIRE_TIMEOUT, pool.acquire()).await;
+    let acquire_ms = started.elapsed().as_millis() as u64;
+    let status = match connection {
+        Ok(Ok(mut conn)) => match sqlx::query("SELECT 1").execute(&mut *conn).await {
+            Ok(_) => "ok",
+            Err(_) => "down",
+        },
+        Ok(Err(_)) => "down",
+        Err(_) => "degraded",
+    };
+    json!({
+        "status": status,
+        "latency_ms": started.elapsed().as_millis() as u64,
+        "acquire_ms": acquire_ms,
+        "pool_size": pool.size(),
+        "pool_idle": pool.num_idle(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    use super::component_status;
+
+    #[tokio::test]
+    async fn exhausted_pool_is_degraded() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        assert_eq!(component_status(&pool).await["status"], "ok");
+
+        let _held = pool.acquire().await.unwrap();
+        assert_eq!(component_status(&pool).await["status"], "degraded");
+    }
+}
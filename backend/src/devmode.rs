@@ -0,0 +1,33 @@
+//! Local development conveniences, switched on with `DEV_MODE=true`. Nothing here
+//! should change behaviour when it's off.
+use std::sync::OnceLock;
+
+use crate::{
+    error::Error,
+    model::database::{Database, DatabaseComponent},
+    plugins::users::User,
+};
+
+pub const DEV_EMAIL: &str = "dev@localhost";
+pub const DEV_PASSWORD: &str = "dev";
+
+/// Log filter used when `RUST_LOG` isn't set, includes every SQL statement.
+pub const DEV_LOG_FILTER: &str = "info,pallet_spaces=debug,sqlx=debug,tower_http=debug";
+
+static ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Reads `DEV_MODE` once, later calls return the same answer.
+pub fn is_enabled() -> bool {
+    *ENABLED.get_or_init(|| std::env::var("DEV_MODE").is_ok_and(|value| value == "true"))
+}
+
+/// Creates the dev login if it doesn't exist yet.
+pub async fn seed(db: &Database) -> Result<(), Error> {
+    if User::from_email(DEV_EMAIL.to_string(), db).await.is_ok() {
+        return Ok(());
+    }
+    let pw_hash = password_auth::generate_hash(DEV_PASSWORD);
+    db.create(User::new("Dev", DEV_EMAIL, &pw_hash)).await?;
+    tracing::info!("Seeded dev user {} / {}", DEV_EMAIL, DEV_PASSWORD);
+    Ok(())
+}
@@ -8,6 +8,7 @@ pub enum Error {
     SocketBind(String),
     Async(String),
     String(String),
+    Config(String),
 }
 
 impl Display for Error {
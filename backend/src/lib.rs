@@ -0,0 +1,110 @@
+// The provider traits are only implemented inside this crate, nobody needs to name
+// their futures' Send bounds.
+#![allow(async_fn_in_trait)]
+
+pub mod appstate;
+pub mod cli;
+pub mod controller;
+pub mod devmode;
+pub mod error;
+pub mod model;
+pub mod plugins;
+pub mod reporting;
+pub mod routes;
+pub mod selfcheck;
+pub mod urls;
+pub mod views;
+use appstate::AppState;
+use axum::{
+    Router,
+    http::{HeaderName, HeaderValue, header::CACHE_CONTROL},
+    middleware::from_fn,
+    routing::get,
+};
+use axum_login::{
+    AuthManagerLayerBuilder,
+    tower_sessions::{MemoryStore, SessionManagerLayer},
+};
+use controller::{
+    Routes, fallback,
+    middleware::{
+        canonical_path_redirect, handle_panic, make_request_span, record_current_user,
+        render_panic_response, stash_request_span,
+    },
+    status::status_json,
+};
+use error::Error;
+use model::database::{Database, DatabaseComponent};
+use plugins::users::User;
+use tower_http::{
+    catch_panic::CatchPanicLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    services::ServeDir,
+    set_header::SetResponseHeaderLayer,
+    trace::TraceLayer,
+};
+use views::home::main_page;
+
+use plugins::{invites::Invite, posts::Post, tickets::Ticket};
+
+pub const PUBLIC_DIR: &str = "./frontend/public/";
+pub const LISTEN_ADDR: ([u8; 4], u16) = ([127, 0, 0, 1], 37373);
+const X_DEV_MODE: HeaderName = HeaderName::from_static("x-dev-mode");
+
+pub async fn create_database() -> Result<Database, Error> {
+    initialise_tables(Database::new().await?).await
+}
+
+/// Creates every plugin's tables, used by `create_database` and by tests on their own file.
+pub async fn initialise_tables(pool: Database) -> Result<Database, Error> {
+    pool.initialise_table::<User>()
+        .await?
+        .initialise_table::<Post>()
+        .await?
+        .initialise_table::<Ticket>()
+        .await?
+        .initialise_table::<Invite>()
+        .await
+}
+
+pub fn create_router(state: AppState) -> Router {
+    let session_layer = SessionManagerLayer::new(MemoryStore::default())
+        .with_secure(state.urls.base().starts_with("https://"));
+    let auth_layer = AuthManagerLayerBuilder::new(state.pool.clone(), session_layer).build();
+    let mut public = Router::new().fallback_service(ServeDir::new(PUBLIC_DIR));
+    if devmode::is_enabled() {
+        public = public.layer(SetResponseHeaderLayer::overriding(
+            CACHE_CONTROL,
+            HeaderValue::from_static("no-store"),
+        ));
+    }
+
+    // Layers run bottom to top, so the request id exists before the span is made,
+    // the span is stashed before the session layer enters its own, and the user is
+    // resolved before it is recorded.
+    let router = Router::new()
+        .route(routes::HOME, get(main_page))
+        .route(routes::STATUS, get(status_json))
+        .add_routes::<User>()
+        .add_routes::<Post>()
+        .add_routes::<Ticket>()
+        .nest(routes::PUBLIC, public)
+        .fallback(fallback)
+        .layer(from_fn(record_current_user))
+        .layer(auth_layer)
+        .layer(from_fn(stash_request_span))
+        .layer(TraceLayer::new_for_http().make_span_with(make_request_span))
+        .layer(CatchPanicLayer::custom(handle_panic))
+        .layer(from_fn(render_panic_response))
+        .layer(from_fn(canonical_path_redirect))
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+        .with_state(state);
+    if devmode::is_enabled() {
+        return router.layer(SetResponseHeaderLayer::overriding(
+            X_DEV_MODE,
+            HeaderValue::from_static("true"),
+        ));
+    }
+    router
+}
@@ -1,38 +1,13 @@
-mod appstate;
-mod controller;
-mod error;
-mod model;
-mod plugins;
-mod views;
-use appstate::AppState;
-use axum::{Router, routing::get};
-use controller::Routes;
-use error::Error;
-use model::database::{Database, DatabaseComponent};
-use plugins::users::User;
+use pallet_spaces::{
+    LISTEN_ADDR, PUBLIC_DIR, appstate::AppState, cli, create_database, create_router, devmode,
+    error::Error, selfcheck, urls::UrlBuilder,
+};
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
-use tower_http::services::ServeDir;
-use views::home::main_page;
-
-use plugins::posts::Post;
-
-async fn create_database() -> Result<Database, Error> {
-    let pool = Database::new().await?;
-    Ok(pool.initialise_table::<User>().await?)
-}
-
-fn create_router(state: AppState) -> Router {
-    Router::new()
-        .route_service("/", get(main_page))
-        .add_routes::<User>()
-        .add_routes::<Post>()
-        .nest_service("/public", ServeDir::new("./frontend/public/"))
-        .with_state(state)
-}
+use tracing_subscriber::EnvFilter;
 
 async fn create_listener() -> Result<TcpListener, Error> {
-    let addr = SocketAddr::from(([127, 0, 0, 1], 37373));
+    let addr = SocketAddr::from(LISTEN_ADDR);
     tracing::info!("Serving app at: http://{}", addr);
     println!("Serving app at: http://{}", addr);
     match TcpListener::bind(addr).await {
@@ -45,14 +20,38 @@ async fn create_listener() -> Result<TcpListener, Error> {
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt::init();
+    let filter = match devmode::is_enabled() {
+        true => EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| EnvFilter::new(devmode::DEV_LOG_FILTER)),
+        false => EnvFilter::from_default_env(),
+    };
+    tracing_subscriber::fmt().with_env_filter(filter).init();
     tracing::info!("Tracing initialised.");
 
     let db = match create_database().await {
         Ok(db) => db,
         Err(err) => panic!("{:?}", err),
     };
-    let state = AppState::new(db);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(command) = args.first() {
+        match cli::run_command(command, &args[1..], &db).await {
+            Ok(_) => return,
+            Err(err) => panic!("{:?}", err),
+        }
+    }
+    if let Err(err) = selfcheck::run(&db, &[PUBLIC_DIR]).await {
+        panic!("{:?}", err);
+    }
+    if devmode::is_enabled() {
+        tracing::warn!("Dev mode is on, never run it in production");
+        if let Err(err) = devmode::seed(&db).await {
+            panic!("{:?}", err);
+        }
+    }
+    let urls = UrlBuilder::from_env(&format!("http://{}", SocketAddr::from(LISTEN_ADDR)));
+    tracing::info!("Public URL: {}", urls.base());
+    let invite_required = std::env::var("INVITE_REQUIRED").is_ok_and(|value| value == "true");
+    let state = AppState::new(db, urls, invite_required);
     let app = create_router(state);
     let listener = match create_listener().await {
         Ok(listener) => listener,
@@ -1,41 +1,104 @@
-use std::ops::{Deref, DerefMut};
+use std::{
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
 
 use async_trait::async_trait;
 use axum_login::{AuthnBackend, UserId};
 use password_auth::verify_password;
-use sqlx::{Pool, Sqlite};
+use sqlx::{
+    Pool, Sqlite,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+};
 use tokio::task;
 
 use crate::error::Error;
 
 use crate::plugins::users::{Credential, User};
 
+/// Waiting longer than this for a connection logs a warning, a sign the pool is
+/// saturated or the file is locked.
+const ACQUIRE_SLOW_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// Connection pools backing the app.
+///
+/// Writes go through `writer`, reads through `reader`. The reader points at the
+/// same file unless `READ_DATABASE` names a replica, but always has its own
+/// connections so slow reads don't hold up transactional writes.
 #[derive(Clone, Debug)]
-pub struct Database(pub Pool<Sqlite>);
+pub struct Database {
+    writer: Pool<Sqlite>,
+    reader: Pool<Sqlite>,
+    /// File the writer was opened on, for error messages.
+    path: String,
+}
 
 impl Database {
     pub async fn new() -> Result<Self, Error> {
-        let opt = sqlx::sqlite::SqliteConnectOptions::new()
-            .filename("test.db")
+        Database::open("test.db").await
+    }
+
+    /// Opens (creating if needed) the database at `filename`, tests use this to get
+    /// a file of their own.
+    pub async fn open(filename: &str) -> Result<Self, Error> {
+        let write_opt = SqliteConnectOptions::new()
+            .filename(filename)
+            .journal_mode(SqliteJournalMode::Wal)
             .create_if_missing(true);
-        match sqlx::sqlite::SqlitePool::connect_with(opt).await {
-            Ok(pool) => Ok(Database(pool)),
-            Err(_) => Err(Error::Database("Failed to create database".into())),
-        }
+        let writer = match pool_options().connect_with(write_opt).await {
+            Ok(pool) => pool,
+            Err(_) => return Err(Error::Database("Failed to create database".into())),
+        };
+
+        let read_file = std::env::var("READ_DATABASE").unwrap_or(filename.into());
+        let read_opt = SqliteConnectOptions::new()
+            .filename(read_file)
+            .read_only(true);
+        let reader = match pool_options().connect_with(read_opt).await {
+            Ok(pool) => pool,
+            Err(_) => return Err(Error::Database("Failed to open read database".into())),
+        };
+
+        Ok(Database {
+            writer,
+            reader,
+            path: filename.to_string(),
+        })
     }
+
+    /// Pool for queries that modify the database.
+    pub fn writer(&self) -> &Pool<Sqlite> {
+        &self.writer
+    }
+
+    /// Pool for read-only queries, e.g. listings and lookups.
+    pub fn reader(&self) -> &Pool<Sqlite> {
+        &self.reader
+    }
+
+    /// Path of the database file writes go to.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+fn pool_options() -> SqlitePoolOptions {
+    SqlitePoolOptions::new()
+        .acquire_slow_level(tracing::log::LevelFilter::Warn)
+        .acquire_slow_threshold(ACQUIRE_SLOW_THRESHOLD)
 }
 
 impl Deref for Database {
     type Target = Pool<Sqlite>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.writer
     }
 }
 
 impl DerefMut for Database {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.writer
     }
 }
 
@@ -56,7 +119,12 @@ where
     async fn initialise_table(pool: Database) -> Result<Database, Error>;
     async fn create(self, pool: &Database) -> Result<&Database, Error>;
     async fn retrieve(id: Self::Id, pool: &Database) -> Result<Self, Error>;
-    async fn update(id: Self::Id, pool: &Database) -> Result<&Database, Error>;
+    /// Tables whose rows are never edited in place can leave this out.
+    async fn update(_id: Self::Id, _pool: &Database) -> Result<&Database, Error> {
+        Err(Error::Database(
+            "Updating this table is not supported".into(),
+        ))
+    }
     async fn delete(id: Self::Id, pool: &Database) -> Result<&Database, Error>;
 }
 
@@ -76,6 +144,8 @@ impl AuthnBackend for Database {
     type Credentials = Credential;
     type Error = Error;
 
+    /// Only checks the password. Suspended users still authenticate so `login_request`
+    /// can show them why they can't log in, `get_user` is what keeps them out.
     async fn authenticate(
         &self,
         creds: Self::Credentials,
@@ -98,14 +168,15 @@ impl AuthnBackend for Database {
         .await?;
         match valid_pass {
             Ok(_) => Ok(Some(user)),
-            Err(_inval) => Err(Error::Database("Invalid password provided".into())),
+            Err(_inval) => Ok(None),
         }
     }
 
     async fn get_user(&self, user_id: &UserId<Self>) -> Result<Option<Self::User>, Self::Error> {
-        let user = sqlx::query_as("select * from users where id = ?")
+        // Suspended users lose their session on their next request
+        let user = sqlx::query_as("select * from users where id = ? and suspended_at is null")
             .bind(user_id)
-            .fetch_optional(&self.0)
+            .fetch_optional(self.reader())
             .await?;
         Ok(user)
     }
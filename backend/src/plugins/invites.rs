@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, sqlx::Type,
+)]
+#[sqlx(transparent)]
+pub struct InviteID(u64);
+
+impl From<u64> for InviteID {
+    fn from(raw: u64) -> Self {
+        InviteID(raw)
+    }
+}
+
+#[derive(Clone, FromRow, Serialize, Deserialize, Debug)]
+pub struct Invite {
+    id: Option<InviteID>,
+    pub code: String,
+    pub max_uses: u32,
+    pub uses: u32,
+}
+
+impl Invite {
+    pub fn new(max_uses: u32) -> Self {
+        let code = Uuid::new_v4().simple().to_string()[..10].to_uppercase();
+        Self {
+            id: None,
+            code,
+            max_uses,
+            uses: 0,
+        }
+    }
+
+    /// Codes are shown upper case but people type them however they like.
+    pub fn normalise_code(code: &str) -> String {
+        code.trim().to_uppercase()
+    }
+}
+
+mod model {
+    use sqlx::Executor;
+
+    use crate::{
+        error::Error,
+        model::database::{Database, DatabaseProvider},
+    };
+
+    use super::Invite;
+
+    impl Invite {
+        /// Claims one use of `code`, failing if it doesn't exist or is used up.
+        pub async fn redeem(code: &str, pool: &Database) -> Result<(), Error> {
+            let result = sqlx::query(
+                "UPDATE invites SET uses = uses + 1 WHERE code=(?1) AND uses < max_uses",
+            )
+            .bind(code)
+            .execute(pool.writer())
+            .await?;
+            match result.rows_affected() {
+                1 => Ok(()),
+                _ => Err(Error::Database("Invite code invalid or used up".into())),
+            }
+        }
+
+        /// Gives back a use claimed by [`Invite::redeem`] when the signup didn't go through.
+        pub async fn release(code: &str, pool: &Database) -> Result<(), Error> {
+            sqlx::query("UPDATE invites SET uses = uses - 1 WHERE code=(?1) AND uses > 0")
+                .bind(code)
+                .execute(pool.writer())
+                .await?;
+            Ok(())
+        }
+
+        /// Remembers which invite brought in which user, for referral analytics.
+        pub async fn record_redemption(
+            code: &str,
+            user_id: u32,
+            pool: &Database,
+        ) -> Result<(), Error> {
+            sqlx::query("INSERT INTO invite_redemptions (code, user_id) VALUES (?1, ?2)")
+                .bind(code)
+                .bind(user_id)
+                .execute(pool.writer())
+                .await?;
+            Ok(())
+        }
+
+        pub async fn from_code(code: &str, pool: &Database) -> Result<Invite, Error> {
+            let invite = sqlx::query_as::<_, Invite>("SELECT * FROM invites WHERE code=(?1)")
+                .bind(code)
+                .fetch_one(pool.reader())
+                .await?;
+            Ok(invite)
+        }
+
+        /// Ids of the users who signed up with `code`.
+        pub async fn redeemed_by(code: &str, pool: &Database) -> Result<Vec<u32>, Error> {
+            let user_ids = sqlx::query_scalar(
+                "SELECT user_id FROM invite_redemptions WHERE code=(?1) ORDER BY id",
+            )
+            .bind(code)
+            .fetch_all(pool.reader())
+            .await?;
+            Ok(user_ids)
+        }
+    }
+
+    impl DatabaseProvider for Invite {
+        type Database = Database;
+        type Id = u32;
+        async fn initialise_table(pool: Database) -> Result<Database, Error> {
+            let invites_attempt = pool
+                .writer()
+                .execute(
+                    "
+      CREATE TABLE if not exists invites (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        code TEXT NOT NULL UNIQUE,
+        max_uses INTEGER NOT NULL,
+        uses INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+      )
+      ",
+                )
+                .await;
+            let redemptions_attempt = pool
+                .writer()
+                .execute(
+                    "
+      CREATE TABLE if not exists invite_redemptions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        code TEXT NOT NULL,
+        user_id INTEGER REFERENCES users(id),
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+      )
+      ",
+                )
+                .await;
+            if invites_attempt.is_err() || redemptions_attempt.is_err() {
+                return Err(Error::Database(
+                    "Failed to create invite database tables".into(),
+                ));
+            }
+            // Redemptions used to be keyed by email, which changes when people edit their
+            // account, so older databases are moved over to user ids
+            let has_user_id: bool = sqlx::query_scalar(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('invite_redemptions') WHERE name = 'user_id'",
+            )
+            .fetch_one(pool.writer())
+            .await?;
+            if !has_user_id {
+                let mut tx = pool.writer().begin().await?;
+                for statement in [
+                    "ALTER TABLE invite_redemptions ADD COLUMN user_id INTEGER REFERENCES users(id)",
+                    "UPDATE invite_redemptions SET user_id = (SELECT id FROM users WHERE users.email = invite_redemptions.email)",
+                    "ALTER TABLE invite_redemptions DROP COLUMN email",
+                ] {
+                    sqlx::query(statement).execute(&mut *tx).await?;
+                }
+                tx.commit().await?;
+            }
+            pool.writer()
+                .execute(
+                    "CREATE UNIQUE INDEX if not exists invite_redemptions_user ON invite_redemptions (user_id)",
+                )
+                .await?;
+            Ok(pool)
+        }
+
+        async fn create(self, pool: &Database) -> Result<&Database, Error> {
+            let attempt =
+                sqlx::query("INSERT INTO invites (code, max_uses, uses) VALUES (?1, ?2, ?3)")
+                    .bind(self.code)
+                    .bind(self.max_uses)
+                    .bind(self.uses)
+                    .execute(pool.writer())
+                    .await;
+            match attempt {
+                Ok(_) => Ok(pool),
+                Err(_) => Err(Error::Database(
+                    "Failed to insert invite into database".into(),
+                )),
+            }
+        }
+
+        async fn retrieve(id: Self::Id, pool: &Database) -> Result<Self, Error> {
+            let attempt = sqlx::query_as::<_, Invite>("SELECT * FROM invites where id=(?1)")
+                .bind(id)
+                .fetch_one(pool.reader())
+                .await;
+            match attempt {
+                Ok(invite) => Ok(invite),
+                Err(_) => Err(Error::Database(
+                    "Failed to retrieve invite from database".into(),
+                )),
+            }
+        }
+
+        async fn delete(id: Self::Id, pool: &Database) -> Result<&Database, Error> {
+            let attempt = sqlx::query("DELETE FROM invites where id=(?1)")
+                .bind(id)
+                .execute(pool.writer())
+                .await;
+            match attempt {
+                Ok(_) => Ok(pool),
+                Err(_) => Err(Error::Database(
+                    "Failed to delete invite from database".into(),
+                )),
+            }
+        }
+    }
+}
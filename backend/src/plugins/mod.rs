@@ -1,2 +1,4 @@
+pub mod invites;
 pub mod posts;
+pub mod tickets;
 pub mod users;
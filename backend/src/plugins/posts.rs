@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
+
+use crate::views::form_errors::FormErrors;
+
+const MAX_NOTES_LENGTH: usize = 2000;
 #[derive(
     Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, sqlx::Type,
 )]
@@ -27,9 +31,30 @@ impl Post {
     }
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct NewPost {
     pub notes: String,
+    /// Rendered fresh with each form, resubmitting the same form reuses it, see
+    /// `Post::create_once`.
+    #[serde(default)]
+    pub submission_token: String,
+}
+
+impl NewPost {
+    pub fn validate(&self) -> FormErrors {
+        let mut errors = FormErrors::default();
+        if self.notes.trim().is_empty() {
+            errors.add("notes", "Describe your space");
+        } else if self.notes.chars().count() > MAX_NOTES_LENGTH {
+            errors.add("notes", "Keep the description under 2000 characters");
+        }
+        errors
+    }
+}
+
+#[derive(Clone, Default, Deserialize)]
+pub struct PostsFilter {
+    pub q: Option<String>,
 }
 
 mod model {
@@ -41,15 +66,69 @@ mod model {
     };
 
     use super::Post;
+
+    /// Listings of suspended hosts are hidden, posts from before owners existed stay up.
+    const VISIBLE: &str = "(owner_id IS NULL OR owner_id NOT IN (SELECT id FROM users WHERE suspended_at IS NOT NULL))";
+
     impl Post {
         pub async fn get_all_posts(pool: &Database) -> Vec<Post> {
-            let mut posts = vec![];
-            for i in 0..20 {
-                if let Ok(post) = Post::retrieve(i, pool).await {
-                    posts.push(post);
-                }
-            }
-            posts
+            let attempt = sqlx::query_as::<_, Post>(&format!(
+                "SELECT * FROM Posts WHERE {} ORDER BY id LIMIT 20",
+                VISIBLE
+            ))
+            .fetch_all(pool.reader())
+            .await;
+            attempt.unwrap_or_default()
+        }
+
+        pub async fn recent_posts(limit: u32, pool: &Database) -> Vec<Post> {
+            let attempt = sqlx::query_as::<_, Post>(&format!(
+                "SELECT * FROM Posts WHERE {} ORDER BY id DESC LIMIT ?1",
+                VISIBLE
+            ))
+            .bind(limit)
+            .fetch_all(pool.reader())
+            .await;
+            attempt.unwrap_or_default()
+        }
+
+        pub async fn count_posts(pool: &Database) -> i64 {
+            let attempt =
+                sqlx::query_scalar(&format!("SELECT COUNT(*) FROM Posts WHERE {}", VISIBLE))
+                    .fetch_one(pool.reader())
+                    .await;
+            attempt.unwrap_or_default()
+        }
+
+        pub async fn search_posts(query: &str, pool: &Database) -> Vec<Post> {
+            let attempt = sqlx::query_as::<_, Post>(&format!(
+                "SELECT * FROM Posts WHERE instr(lower(notes), lower(?1)) > 0 AND {} LIMIT 20",
+                VISIBLE
+            ))
+            .bind(query)
+            .fetch_all(pool.reader())
+            .await;
+            attempt.unwrap_or_default()
+        }
+
+        /// Inserts the post unless `owner_id` already submitted a form with this
+        /// `submission_token`, so a double-clicked create form lists the space once even
+        /// when both requests race. Returns whether a row was added.
+        pub async fn create_once(
+            self,
+            owner_id: u32,
+            submission_token: &str,
+            pool: &Database,
+        ) -> Result<bool, Error> {
+            let result = sqlx::query(
+                "INSERT INTO Posts (notes, owner_id, submission_token) VALUES (?1, ?2, ?3) ON CONFLICT DO NOTHING",
+            )
+            .bind(self.notes)
+            .bind(owner_id)
+            .bind(submission_token)
+            .execute(pool.writer())
+            .await?;
+            Ok(result.rows_affected() == 1)
         }
     }
 
@@ -64,28 +143,53 @@ mod model {
         type Id = u32;
         async fn initialise_table(pool: Database) -> Result<Database, Error> {
             let creation_attempt = &pool
-                .0
+                .writer()
                 .execute(
                     "
       CREATE TABLE if not exists Posts (
         id INTEGER PRIMARY KEY AUTOINCREMENT,
         notes TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        owner_id INTEGER REFERENCES users(id),
+        submission_token TEXT
       )
       ",
                 )
                 .await;
-            match creation_attempt {
-                Ok(_) => Ok(pool),
-                Err(_) => Err(Error::Database(
+            if creation_attempt.is_err() {
+                return Err(Error::Database(
                     "Failed to create Post database tables".into(),
-                )),
+                ));
             }
+            // Databases made before submission tokens existed are missing the columns
+            for (column, definition) in [
+                ("owner_id", "owner_id INTEGER REFERENCES users(id)"),
+                ("submission_token", "submission_token TEXT"),
+            ] {
+                let has_column: bool = sqlx::query_scalar(
+                    "SELECT COUNT(*) > 0 FROM pragma_table_info('Posts') WHERE name = ?1",
+                )
+                .bind(column)
+                .fetch_one(pool.writer())
+                .await?;
+                if !has_column {
+                    pool.writer()
+                        .execute(format!("ALTER TABLE Posts ADD COLUMN {}", definition).as_str())
+                        .await?;
+                }
+            }
+            pool.writer()
+                .execute(
+                    "CREATE UNIQUE INDEX if not exists posts_submission ON Posts (owner_id, submission_token)",
+                )
+                .await?;
+            Ok(pool)
         }
 
         async fn create(self, pool: &Database) -> Result<&Database, Error> {
             let attempt = sqlx::query("INSERT INTO Posts (notes) VALUES (?1)")
                 .bind(self.notes)
-                .execute(&pool.0)
+                .execute(pool.writer())
                 .await;
             match attempt {
                 Ok(_) => Ok(pool),
@@ -98,7 +202,7 @@ mod model {
         async fn retrieve(id: Self::Id, pool: &Database) -> Result<Self, Error> {
             let attempt = sqlx::query_as::<_, Post>("SELECT * FROM Posts where id=(?1)")
                 .bind(id)
-                .fetch_one(&pool.0)
+                .fetch_one(pool.reader())
                 .await;
             match attempt {
                 Ok(post) => Ok(post),
@@ -108,12 +212,17 @@ mod model {
             }
         }
 
-        async fn update(id: Self::Id, pool: &Database) -> Result<&Database, Error> {
-            todo!()
-        }
-
         async fn delete(id: Self::Id, pool: &Database) -> Result<&Database, Error> {
-            todo!()
+            let attempt = sqlx::query("DELETE FROM Posts where id=(?1)")
+                .bind(id)
+                .execute(pool.writer())
+                .await;
+            match attempt {
+                Ok(_) => Ok(pool),
+                Err(_) => Err(Error::Database(
+                    "Failed to delete post from database".into(),
+                )),
+            }
         }
     }
 }
@@ -121,81 +230,220 @@ mod model {
 mod control {
     use axum::{
         Form, Router,
-        extract::State,
-        http::StatusCode,
-        routing::{get},
+        extract::{Query, State},
+        http::{HeaderMap, StatusCode},
+        routing::get,
     };
+    use axum_login::AuthUser;
     use maud::Markup;
+    use uuid::Uuid;
 
     use crate::{
         appstate::AppState,
-        controller::RouteProvider,
-        model::database::DatabaseComponent,
-        plugins::posts::view::{new_post_failure, new_post_success},
+        controller::{RouteProvider, auth::RequireUser},
+        plugins::posts::view::{new_post_success, post_form, post_list},
+        routes,
+        views::{context::ViewContext, form_errors::FormErrors},
     };
 
-    use super::{NewPost, Post, view::create_post_page};
+    use super::{NewPost, Post, PostsFilter, view::create_post_page};
 
     impl RouteProvider for Post {
         fn provide_routes(router: Router<AppState>) -> Router<AppState> {
-            router
+            let router = router
                 .route(
-                    "/new_post",
+                    routes::NEW_POST,
                     get(Post::create_post_page).post(Post::new_post_request),
                 )
-                .route("/Posts", get(Post::post_list))
+                .route(routes::POSTS, get(Post::post_list));
+            #[cfg(feature = "loadtest")]
+            let router = router.route(
+                routes::LOADTEST_POSTS,
+                axum::routing::post(Post::generate_posts_request),
+            );
+            router
         }
     }
 
     impl Post {
-        pub async fn create_post_page() -> (StatusCode, Markup) {
-            (StatusCode::OK, create_post_page().await)
+        pub async fn create_post_page(
+            RequireUser(_user): RequireUser,
+            context: ViewContext,
+        ) -> (StatusCode, Markup) {
+            let form = post_form(&NewPost::default(), &FormErrors::default());
+            (StatusCode::OK, create_post_page(&context, form).await)
+        }
+
+        /// Shows the post form again with `errors` next to the fields, htmx only
+        /// needs the form itself.
+        async fn post_errors(
+            context: &ViewContext,
+            headers: &HeaderMap,
+            status: StatusCode,
+            payload: &NewPost,
+            errors: &FormErrors,
+        ) -> (StatusCode, Markup) {
+            let form = post_form(payload, errors);
+            match headers.contains_key("hx-request") {
+                true => (status, form),
+                false => (status, create_post_page(context, form).await),
+            }
         }
 
         pub async fn new_post_request(
+            RequireUser(user): RequireUser,
+            context: ViewContext,
             State(state): State<AppState>,
+            headers: HeaderMap,
             Form(payload): Form<NewPost>,
         ) -> (StatusCode, Markup) {
+            let errors = payload.validate();
+            if !errors.is_empty() {
+                return Post::post_errors(
+                    &context,
+                    &headers,
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    &payload,
+                    &errors,
+                )
+                .await;
+            }
+            // Forms rendered before tokens existed can't be deduplicated, but still post
+            let submission_token = match payload.submission_token.is_empty() {
+                true => Uuid::new_v4().to_string(),
+                false => payload.submission_token.clone(),
+            };
             let post = Post::new(&payload.notes);
-            tracing::debug!("Signing up Post {:?}", post);
-            let insert_result = state.pool.create(post).await;
+            tracing::debug!("Creating Post {:?}", post);
+            let insert_result = post
+                .create_once(user.id(), &submission_token, &state.pool)
+                .await;
             tracing::debug!("Creation success {:?}", insert_result);
             match insert_result {
-                Ok(_) => (StatusCode::OK, new_post_success().await),
-                Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, new_post_failure().await),
+                Ok(created) => {
+                    if !created {
+                        tracing::debug!("Duplicate submission {}", submission_token);
+                    }
+                    (StatusCode::OK, new_post_success().await)
+                }
+                Err(_) => {
+                    let failed = FormErrors::form("We couldn't list your space, please try again");
+                    Post::post_errors(
+                        &context,
+                        &headers,
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        &payload,
+                        &failed,
+                    )
+                    .await
+                }
             }
         }
 
-        pub async fn post_list(State(state): State<AppState>) -> (StatusCode, Markup) {
-            let contents = maud::html! { ol {
-                @for post in Post::get_all_posts(&state.pool).await {
-                    li { (post) }
-                }
-            }};
-            (StatusCode::OK, contents)
+        pub async fn post_list(
+            State(state): State<AppState>,
+            Query(filter): Query<PostsFilter>,
+        ) -> (StatusCode, Markup) {
+            let posts = match &filter.q {
+                Some(query) if !query.is_empty() => Post::search_posts(query, &state.pool).await,
+                _ => Post::get_all_posts(&state.pool).await,
+            };
+            (StatusCode::OK, post_list(&posts))
+        }
+    }
+}
+
+/// Synthetic data for load testing, only built with `--features loadtest`.
+#[cfg(feature = "loadtest")]
+mod loadtest {
+    use axum::{
+        Json,
+        extract::{Query, State},
+        http::StatusCode,
+    };
+    use serde::Deserialize;
+    use serde_json::{Value, json};
+
+    use crate::{appstate::AppState, error::Error, model::database::Database};
+
+    use super::Post;
+
+    const MAX_GENERATED: u32 = 100_000;
+
+    #[derive(Deserialize)]
+    pub struct GenerateParams {
+        pub count: u32,
+    }
+
+    impl Post {
+        pub async fn generate_posts(count: u32, pool: &Database) -> Result<u32, Error> {
+            let count = count.min(MAX_GENERATED);
+            let mut tx = pool.writer().begin().await?;
+            for i in 0..count {
+                sqlx::query("INSERT INTO Posts (notes) VALUES (?1)")
+                    .bind(format!("Load test space {} ({} pallets)", i, i % 40 + 1))
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await?;
+            Ok(count)
+        }
+
+        pub async fn generate_posts_request(
+            State(state): State<AppState>,
+            Query(params): Query<GenerateParams>,
+        ) -> (StatusCode, Json<Value>) {
+            match Post::generate_posts(params.count, &state.pool).await {
+                Ok(created) => (StatusCode::OK, Json(json!({ "created": created }))),
+                Err(err) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({ "error": err.to_string() })),
+                ),
+            }
         }
     }
 }
 
 mod view {
     use maud::{Markup, html};
+    use uuid::Uuid;
 
-    use crate::views::utils::{default_header, title_and_navbar};
+    use crate::{
+        routes,
+        views::{
+            context::ViewContext,
+            form_errors::{FormErrors, field_error},
+            utils::{default_header, title_and_navbar},
+        },
+    };
+
+    use super::{NewPost, Post};
 
-    pub async fn create_post_page() -> Markup {
+    pub async fn create_post_page(context: &ViewContext, form: Markup) -> Markup {
         html! {
-            (default_header("Pallet Spaces: Signup"))
-            (title_and_navbar())
+            (default_header("Pallet Spaces: List a space"))
+            (title_and_navbar(context))
             body {
-                form id="signupForm" action="signup" method="POST" hx-post="/signup" {
-                    label for="Fullname" { "Fullname:" }
-                    input type="text" id="name" name="name" {}
-                    br {}
-                    label for="Password" { "Password:" }
-                    input type="text" id="password" name="password" {}
-                    br {}
-                    button type="submit" { "Submit" }
-                }
+                (form)
+            }
+        }
+    }
+
+    pub fn post_form(values: &NewPost, errors: &FormErrors) -> Markup {
+        // Keep the token when showing errors so fixing them and resubmitting still dedupes
+        let submission_token = match values.submission_token.is_empty() {
+            true => Uuid::new_v4().to_string(),
+            false => values.submission_token.clone(),
+        };
+        html! {
+            form id="postForm" action=(routes::NEW_POST) method="POST" hx-post=(routes::NEW_POST) hx-target="this" hx-swap="outerHTML" {
+                (field_error(errors, FormErrors::FORM))
+                label for="notes" { "Describe your space:" }
+                textarea id="notes" name="notes" { (values.notes) }
+                (field_error(errors, "notes"))
+                br {}
+                input type="hidden" name="submission_token" value=(submission_token) {}
+                button type="submit" { "Submit" }
             }
         }
     }
@@ -203,29 +451,63 @@ mod view {
     pub async fn new_post_success() -> Markup {
         // This should redirect to the new post
         html! {
-            (default_header("Pallet Spaces: Signup"))
+            (default_header("Pallet Spaces: List a space"))
             body {
                 h2 {
-                    "Thanks for signing up"
+                    "Your space is listed"
                 }
                 p {
-                    "We'll be in touch soon if theres enough interest"
+                    a href=(routes::POSTS) { "See all spaces" }
                 }
             }
         }
     }
 
-    pub async fn new_post_failure() -> Markup {
-        html! {
-            (default_header("Pallet Spaces: Signup"))
-            body {
-                h2 {
-                    "Attempted signup failed"
-                }
-                p {
-                    "Please try again"
-                }
+    pub fn post_list(posts: &[Post]) -> Markup {
+        html! { ol {
+            @for post in posts {
+                li { (post) }
             }
+        }}
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{create_post_page, new_post_success, post_form, post_list};
+        use crate::{
+            plugins::posts::{NewPost, Post},
+            views::{context::ViewContext, form_errors::FormErrors, snapshot::normalise},
+        };
+
+        #[tokio::test]
+        async fn create_post_page_form() {
+            let form = post_form(&NewPost::default(), &FormErrors::default());
+            insta::assert_snapshot!(normalise(
+                create_post_page(&ViewContext::default(), form).await
+            ));
+        }
+
+        #[test]
+        fn post_form_keeps_notes_with_errors() {
+            let values = NewPost {
+                notes: " ".to_string(),
+                ..Default::default()
+            };
+            insta::assert_snapshot!(normalise(post_form(&values, &values.validate())));
+        }
+
+        #[tokio::test]
+        async fn new_post_results() {
+            insta::assert_snapshot!("new_post_success", normalise(new_post_success().await));
+        }
+
+        #[test]
+        fn post_list_items() {
+            let posts = [
+                Post::new(&"Dry store in Dandenong, 12 pallets".to_string()),
+                Post::new(&"Cold room in Footscray, 4 pallets".to_string()),
+            ];
+            insta::assert_snapshot!(normalise(post_list(&posts)));
         }
     }
 }
@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use crate::controller::form_protection::FormProtection;
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, sqlx::Type,
+)]
+#[sqlx(transparent)]
+pub struct TicketID(u64);
+
+impl From<u64> for TicketID {
+    fn from(raw: u64) -> Self {
+        TicketID(raw)
+    }
+}
+
+pub const TICKET_CATEGORIES: [&str; 4] = ["General", "Account", "Listing", "Payment"];
+
+#[derive(Clone, FromRow, Serialize, Deserialize, Debug)]
+pub struct Ticket {
+    id: Option<TicketID>,
+    pub category: String,
+    pub email: String,
+    pub message: String,
+    pub status: String,
+}
+
+impl Ticket {
+    pub fn new(category: &str, email: &str, message: &str) -> Self {
+        Self {
+            id: None,
+            category: category.to_string(),
+            email: email.to_string(),
+            message: message.to_string(),
+            status: "open".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct NewTicket {
+    pub category: String,
+    pub email: String,
+    pub message: String,
+    #[serde(flatten)]
+    pub protection: FormProtection,
+}
+
+impl NewTicket {
+    pub fn is_valid(&self) -> bool {
+        TICKET_CATEGORIES.contains(&self.category.as_str())
+            && self.email.contains('@')
+            && !self.message.trim().is_empty()
+    }
+}
+
+mod model {
+    use sqlx::Executor;
+
+    use crate::{
+        error::Error,
+        model::database::{Database, DatabaseProvider},
+    };
+
+    use super::Ticket;
+
+    impl DatabaseProvider for Ticket {
+        type Database = Database;
+        type Id = u32;
+        async fn initialise_table(pool: Database) -> Result<Database, Error> {
+            let creation_attempt = &pool
+                .writer()
+                .execute(
+                    "
+      CREATE TABLE if not exists tickets (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        category TEXT NOT NULL,
+        email TEXT NOT NULL,
+        message TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'open',
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+      )
+      ",
+                )
+                .await;
+            match creation_attempt {
+                Ok(_) => Ok(pool),
+                Err(_) => Err(Error::Database(
+                    "Failed to create ticket database tables".into(),
+                )),
+            }
+        }
+
+        async fn create(self, pool: &Database) -> Result<&Database, Error> {
+            let attempt = sqlx::query(
+                "INSERT INTO tickets (category, email, message, status) VALUES (?1, ?2, ?3, ?4)",
+            )
+            .bind(self.category)
+            .bind(self.email)
+            .bind(self.message)
+            .bind(self.status)
+            .execute(pool.writer())
+            .await;
+            match attempt {
+                Ok(_) => Ok(pool),
+                Err(_) => Err(Error::Database(
+                    "Failed to insert ticket into database".into(),
+                )),
+            }
+        }
+
+        async fn retrieve(id: Self::Id, pool: &Database) -> Result<Self, Error> {
+            let attempt = sqlx::query_as::<_, Ticket>("SELECT * FROM tickets where id=(?1)")
+                .bind(id)
+                .fetch_one(pool.reader())
+                .await;
+            match attempt {
+                Ok(ticket) => Ok(ticket),
+                Err(_) => Err(Error::Database(
+                    "Failed to retrieve ticket from database".into(),
+                )),
+            }
+        }
+
+        async fn delete(id: Self::Id, pool: &Database) -> Result<&Database, Error> {
+            let attempt = sqlx::query("DELETE FROM tickets where id=(?1)")
+                .bind(id)
+                .execute(pool.writer())
+                .await;
+            match attempt {
+                Ok(_) => Ok(pool),
+                Err(_) => Err(Error::Database(
+                    "Failed to delete ticket from database".into(),
+                )),
+            }
+        }
+    }
+}
+
+mod control {
+    use axum::{Form, Router, extract::State, http::StatusCode, routing::get};
+    use maud::Markup;
+
+    use crate::{
+        appstate::AppState, controller::RouteProvider, model::database::DatabaseComponent, routes,
+        views::context::ViewContext,
+    };
+
+    use super::{
+        NewTicket, Ticket,
+        view::{support_page, ticket_failure, ticket_success},
+    };
+
+    impl RouteProvider for Ticket {
+        fn provide_routes(router: Router<AppState>) -> Router<AppState> {
+            router.route(
+                routes::SUPPORT,
+                get(Ticket::support_page).post(Ticket::new_ticket_request),
+            )
+        }
+    }
+
+    impl Ticket {
+        pub async fn support_page(context: ViewContext) -> (StatusCode, Markup) {
+            (StatusCode::OK, support_page(&context).await)
+        }
+
+        pub async fn new_ticket_request(
+            State(state): State<AppState>,
+            Form(payload): Form<NewTicket>,
+        ) -> (StatusCode, Markup) {
+            if payload.protection.is_probably_bot() || !payload.is_valid() {
+                return (StatusCode::BAD_REQUEST, ticket_failure().await);
+            }
+            let ticket = Ticket::new(&payload.category, &payload.email, &payload.message);
+            tracing::debug!("Opening ticket {:?}", ticket);
+            let insert_result = state.pool.create(ticket).await;
+            tracing::debug!("Creation success {:?}", insert_result);
+            match insert_result {
+                Ok(_) => (StatusCode::OK, ticket_success().await),
+                Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, ticket_failure().await),
+            }
+        }
+    }
+}
+
+mod view {
+    use maud::{Markup, html};
+
+    use crate::{
+        routes,
+        views::{
+            context::ViewContext,
+            utils::{default_header, form_protection_fields, title_and_navbar},
+        },
+    };
+
+    use super::TICKET_CATEGORIES;
+
+    pub async fn support_page(context: &ViewContext) -> Markup {
+        html! {
+            (default_header("Pallet Spaces: Support"))
+            (title_and_navbar(context))
+            body {
+                form id="supportForm" action=(routes::SUPPORT) method="POST" hx-post=(routes::SUPPORT) {
+                    label for="category" { "Category:" }
+                    select id="category" name="category" {
+                        @for category in TICKET_CATEGORIES {
+                            option value=(category) { (category) }
+                        }
+                    }
+                    br {}
+                    label for="email" { "E-mail:" }
+                    input type="text" id="email" name="email" {}
+                    br {}
+                    label for="message" { "Message:" }
+                    textarea id="message" name="message" {}
+                    br {}
+                    (form_protection_fields())
+                    button type="submit" { "Submit" }
+                }
+            }
+        }
+    }
+
+    pub async fn ticket_success() -> Markup {
+        html! {
+            (default_header("Pallet Spaces: Support"))
+            body {
+                h2 {
+                    "Thanks, we've got your message"
+                }
+                p {
+                    "We'll reply by email as soon as we can"
+                }
+            }
+        }
+    }
+
+    pub async fn ticket_failure() -> Markup {
+        html! {
+            (default_header("Pallet Spaces: Support"))
+            body {
+                h2 {
+                    "We couldn't send your message"
+                }
+                p {
+                    "Check your email and message, then try again"
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{support_page, ticket_failure, ticket_success};
+        use crate::views::{context::ViewContext, snapshot::normalise};
+
+        #[tokio::test]
+        async fn support_page_form() {
+            insta::assert_snapshot!(normalise(support_page(&ViewContext::default()).await));
+        }
+
+        #[tokio::test]
+        async fn ticket_results() {
+            insta::assert_snapshot!("ticket_success", normalise(ticket_success().await));
+            insta::assert_snapshot!("ticket_failure", normalise(ticket_failure().await));
+        }
+    }
+}
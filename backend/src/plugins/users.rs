@@ -2,6 +2,10 @@ use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 use tracing::debug;
 
+use crate::{controller::form_protection::FormProtection, views::form_errors::FormErrors};
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+
 #[derive(
     Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, sqlx::Type,
 )]
@@ -20,19 +24,69 @@ pub struct User {
     pub name: String,
     pub email: String,
     pub pw_hash: String,
+    /// Set when an admin suspends the account, suspended users can't log in.
+    pub suspended_at: Option<String>,
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct SignupUser {
     pub name: String,
     pub email: String,
     pub password: String,
+    #[serde(default)]
+    pub invite_code: String,
+    #[serde(flatten)]
+    pub protection: FormProtection,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Credential {
     pub email: String,
     pub password: String,
+    /// Where to go after logging in, see `controller::auth::safe_next`.
+    #[serde(default)]
+    pub next: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LoginParams {
+    pub next: Option<String>,
+}
+
+impl SignupUser {
+    pub fn validate(&self) -> FormErrors {
+        let mut errors = FormErrors::default();
+        if self.name.trim().is_empty() {
+            errors.add("name", "Enter your name");
+        }
+        if !is_valid_email(&self.email) {
+            errors.add("email", "Enter a valid email address");
+        }
+        if self.password.chars().count() < MIN_PASSWORD_LENGTH {
+            errors.add("password", "Use at least 8 characters");
+        }
+        errors
+    }
+}
+
+pub fn is_valid_email(email: &str) -> bool {
+    // Actually a hard problem, can be better solved(see: https://david-gilbertson.medium.com/the-100-correct-way-to-validate-email-addresses-7c4818f24643)
+    // but for now
+    // check there exits an @
+    let mut valid = email.contains('@');
+
+    // Check text is either side of the email
+    let results = email.split('@').collect::<Vec<&str>>();
+    let mut res_iter = results.iter();
+    valid &= match res_iter.next() {
+        Some(a) => !a.is_empty(),
+        None => false,
+    };
+    valid &= match res_iter.next() {
+        Some(a) => !a.is_empty(),
+        None => false,
+    };
+    valid
 }
 
 impl User {
@@ -42,6 +96,7 @@ impl User {
             name: name.to_string(),
             email: email.to_string(),
             pw_hash: password.to_string(),
+            suspended_at: None,
         };
         debug!("Made new user {:?}", user);
         user
@@ -63,7 +118,7 @@ mod model {
             tracing::info!("{}", email);
             let user: User = sqlx::query_as("select * from users where email = ? ")
                 .bind(email)
-                .fetch_one(&pool.0)
+                .fetch_one(pool.reader())
                 .await?;
             tracing::debug!("{:?}", user);
             Ok(user)
@@ -78,6 +133,29 @@ mod model {
             }
             users
         }
+
+        pub fn is_suspended(&self) -> bool {
+            self.suspended_at.is_some()
+        }
+
+        /// Suspends or reinstates the account with `email`, keeping all its data.
+        pub async fn set_suspended(
+            email: &str,
+            suspended: bool,
+            pool: &Database,
+        ) -> Result<(), Error> {
+            let result = sqlx::query(
+                "UPDATE users SET suspended_at = CASE WHEN ?2 THEN CURRENT_TIMESTAMP END WHERE email = ?1",
+            )
+            .bind(email)
+            .bind(suspended)
+            .execute(pool.writer())
+            .await?;
+            match result.rows_affected() {
+                1 => Ok(()),
+                _ => Err(Error::Database(format!("No user with email {}", email))),
+            }
+        }
     }
 
     impl std::fmt::Debug for User {
@@ -87,6 +165,7 @@ mod model {
                 .field("name", &self.name)
                 .field("email", &self.email)
                 .field("password", &"[REDACTED]")
+                .field("suspended_at", &self.suspended_at)
                 .finish()
         }
     }
@@ -102,24 +181,36 @@ mod model {
         type Id = u32;
         async fn initialise_table(pool: Database) -> Result<Database, Error> {
             let creation_attempt = &pool
-                .0
+                .writer()
                 .execute(
                     "
       CREATE TABLE if not exists users (
         id INTEGER PRIMARY KEY AUTOINCREMENT,
         name TEXT NOT NULL,
         email TEXT NOT NULL UNIQUE,
-        pw_hash TEXT NOT NULL
+        pw_hash TEXT NOT NULL,
+        suspended_at TEXT
       )
       ",
                 )
                 .await;
-            match creation_attempt {
-                Ok(_) => Ok(pool),
-                Err(_) => Err(Error::Database(
+            if creation_attempt.is_err() {
+                return Err(Error::Database(
                     "Failed to create user database tables".into(),
-                )),
+                ));
+            }
+            // Databases made before suspension existed are missing the column
+            let has_suspended_at: bool = sqlx::query_scalar(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('users') WHERE name = 'suspended_at'",
+            )
+            .fetch_one(pool.writer())
+            .await?;
+            if !has_suspended_at {
+                pool.writer()
+                    .execute("ALTER TABLE users ADD COLUMN suspended_at TEXT")
+                    .await?;
             }
+            Ok(pool)
         }
 
         async fn create(self, pool: &Database) -> Result<&Database, Error> {
@@ -128,7 +219,7 @@ mod model {
                     .bind(self.name)
                     .bind(self.email)
                     .bind(self.pw_hash)
-                    .execute(&pool.0)
+                    .execute(pool.writer())
                     .await;
             match attempt {
                 Ok(_) => Ok(pool),
@@ -141,7 +232,7 @@ mod model {
         async fn retrieve(id: Self::Id, pool: &Database) -> Result<Self, Error> {
             let attempt = sqlx::query_as::<_, User>("SELECT * FROM users where id=(?1)")
                 .bind(id)
-                .fetch_one(&pool.0)
+                .fetch_one(pool.reader())
                 .await;
             match attempt {
                 Ok(user) => Ok(user),
@@ -151,12 +242,17 @@ mod model {
             }
         }
 
-        async fn update(id: Self::Id, pool: &Database) -> Result<&Database, Error> {
-            todo!()
-        }
-
         async fn delete(id: Self::Id, pool: &Database) -> Result<&Database, Error> {
-            todo!()
+            let attempt = sqlx::query("DELETE FROM users where id=(?1)")
+                .bind(id)
+                .execute(pool.writer())
+                .await;
+            match attempt {
+                Ok(_) => Ok(pool),
+                Err(_) => Err(Error::Database(
+                    "Failed to delete user from database".into(),
+                )),
+            }
         }
     }
 
@@ -171,7 +267,7 @@ mod model {
         }
 
         fn session_auth_hash(&self) -> &[u8] {
-            &self.pw_hash.as_bytes()
+            self.pw_hash.as_bytes()
         }
     }
 }
@@ -179,95 +275,263 @@ mod model {
 mod control {
     use axum::{
         Form, Router,
-        extract::State,
-        http::StatusCode,
+        extract::{Query, State},
+        http::{HeaderMap, StatusCode},
+        response::{IntoResponse, Redirect, Response},
         routing::{get, post},
     };
+    use axum_login::{AuthSession, AuthUser};
     use maud::Markup;
 
     use crate::{
-        appstate::AppState, controller::RouteProvider, model::database::DatabaseComponent,
-        views::utils::page_not_found,
+        appstate::AppState,
+        controller::{
+            RouteProvider,
+            auth::{RequireUser, safe_next},
+        },
+        model::database::{Database, DatabaseComponent},
+        plugins::invites::Invite,
+        routes,
+        views::{context::ViewContext, form_errors::FormErrors},
     };
 
     use super::{
-        Credential, SignupUser, User,
-        view::{email_form_html, login_page, signup_failure, signup_page, signup_success},
+        Credential, LoginParams, SignupUser, User, is_valid_email,
+        view::{
+            email_form_html, login_form, login_page, signup_form, signup_page, signup_success,
+            suspended_page,
+        },
     };
 
     impl RouteProvider for User {
         fn provide_routes(router: Router<AppState>) -> Router<AppState> {
             router
-                .route("/signup", get(User::signup_page).post(User::signup_request))
-                .route("/signup/email", post(User::email_validation))
-                .route("/login", get(User::login_page).post(User::login_request))
-                .route("/users", get(User::user_list))
+                .route(
+                    routes::SIGNUP,
+                    get(User::signup_page).post(User::signup_request),
+                )
+                .route(routes::SIGNUP_EMAIL, post(User::email_validation))
+                .route(
+                    routes::LOGIN,
+                    get(User::login_page).post(User::login_request),
+                )
+                .route(routes::USERS, get(User::user_list))
         }
     }
 
+    /// Same message for unknown emails and wrong passwords, so the form doesn't reveal
+    /// who has an account.
+    const WRONG_CREDENTIALS: &str = "Wrong email or password";
+
     impl User {
-        pub async fn signup_page() -> (StatusCode, Markup) {
-            (StatusCode::OK, signup_page().await)
+        pub async fn signup_page(context: ViewContext) -> (StatusCode, Markup) {
+            let form = signup_form(&SignupUser::default(), &FormErrors::default());
+            (StatusCode::OK, signup_page(&context, form).await)
+        }
+
+        /// Shows the signup form again with `errors` next to the fields, htmx only
+        /// needs the form itself.
+        async fn signup_errors(
+            context: &ViewContext,
+            headers: &HeaderMap,
+            status: StatusCode,
+            payload: &SignupUser,
+            errors: &FormErrors,
+        ) -> (StatusCode, Markup) {
+            let form = signup_form(payload, errors);
+            match headers.contains_key("hx-request") {
+                true => (status, form),
+                false => (status, signup_page(context, form).await),
+            }
         }
 
         pub async fn signup_request(
             State(state): State<AppState>,
+            context: ViewContext,
+            headers: HeaderMap,
             Form(payload): Form<SignupUser>,
         ) -> (StatusCode, Markup) {
+            if payload.protection.is_probably_bot() {
+                tracing::debug!("Rejected signup from probable bot");
+                let errors = FormErrors::form("Something went wrong, please try again");
+                return User::signup_errors(
+                    &context,
+                    &headers,
+                    StatusCode::BAD_REQUEST,
+                    &payload,
+                    &errors,
+                )
+                .await;
+            }
+            let mut errors = payload.validate();
+            if errors.is_empty()
+                && User::from_email(payload.email.clone(), &state.pool)
+                    .await
+                    .is_ok()
+            {
+                errors.add(
+                    "email",
+                    "There's already an account for this email, try logging in",
+                );
+            }
+            if !errors.is_empty() {
+                return User::signup_errors(
+                    &context,
+                    &headers,
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    &payload,
+                    &errors,
+                )
+                .await;
+            }
+            let invite_code = Invite::normalise_code(&payload.invite_code);
+            if invite_code.is_empty() && state.invite_required {
+                let mut errors = FormErrors::default();
+                errors.add(
+                    "invite_code",
+                    "Signups are invite only at the moment, enter your code",
+                );
+                return User::signup_errors(
+                    &context,
+                    &headers,
+                    StatusCode::FORBIDDEN,
+                    &payload,
+                    &errors,
+                )
+                .await;
+            }
+            if !invite_code.is_empty() && Invite::redeem(&invite_code, &state.pool).await.is_err() {
+                let mut errors = FormErrors::default();
+                errors.add(
+                    "invite_code",
+                    "That invite code isn't valid or has been used up",
+                );
+                return User::signup_errors(
+                    &context,
+                    &headers,
+                    StatusCode::FORBIDDEN,
+                    &payload,
+                    &errors,
+                )
+                .await;
+            }
             let pw_hash = password_auth::generate_hash(&payload.password);
             let user = User::new(&payload.name, &payload.email, &pw_hash);
             tracing::debug!("Signing up user {:?}", user);
             let insert_result = state.pool.create(user).await;
             tracing::debug!("Creation success {:?}", insert_result);
             match insert_result {
-                Ok(_) => (StatusCode::OK, signup_success().await),
-                Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, signup_failure().await),
+                Ok(_) => {
+                    if !invite_code.is_empty() {
+                        let recorded =
+                            match User::from_email(payload.email.clone(), &state.pool).await {
+                                Ok(user) => {
+                                    Invite::record_redemption(&invite_code, user.id(), &state.pool)
+                                        .await
+                                }
+                                Err(err) => Err(err),
+                            };
+                        tracing::debug!("Invite redemption recorded {:?}", recorded);
+                    }
+                    (StatusCode::OK, signup_success().await)
+                }
+                Err(_) => {
+                    if !invite_code.is_empty() {
+                        let released = Invite::release(&invite_code, &state.pool).await;
+                        tracing::debug!("Invite released {:?}", released);
+                    }
+                    let errors =
+                        FormErrors::form("We couldn't create your account, please try again");
+                    User::signup_errors(
+                        &context,
+                        &headers,
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        &payload,
+                        &errors,
+                    )
+                    .await
+                }
             }
         }
 
         pub async fn email_validation(Form(payload): Form<SignupUser>) -> (StatusCode, Markup) {
-            // Actually a hard problem, can be better solved(see: https://david-gilbertson.medium.com/the-100-correct-way-to-validate-email-addresses-7c4818f24643)
-            // but for now
-            // check there exits an @
-            let mut valid = payload.email.contains('@');
-
-            // Check text is either side of the email
-            let results = payload.email.split('@').collect::<Vec<&str>>();
-            let mut res_iter = results.iter();
-            valid &= match res_iter.next() {
-                Some(a) => !a.is_empty(),
-                None => false,
-            };
-            valid &= match res_iter.next() {
-                Some(a) => !a.is_empty(),
-                None => false,
-            };
-
-            (StatusCode::OK, email_form_html(valid, &payload.email))
+            let mut errors = FormErrors::default();
+            if !is_valid_email(&payload.email) {
+                errors.add("email", "Enter a valid email address");
+            }
+            (StatusCode::OK, email_form_html(&payload.email, &errors))
         }
 
         // Login
-        pub async fn login_page() -> (StatusCode, Markup) {
-            (StatusCode::OK, login_page().await)
+        pub async fn login_page(
+            context: ViewContext,
+            Query(params): Query<LoginParams>,
+        ) -> (StatusCode, Markup) {
+            let next = params.next.unwrap_or_default();
+            let form = login_form(safe_next(&next), "", &FormErrors::default());
+            (StatusCode::OK, login_page(&context, form).await)
+        }
+
+        /// Like `signup_errors`, `form` already has the messages in it.
+        async fn login_errors(
+            context: &ViewContext,
+            headers: &HeaderMap,
+            status: StatusCode,
+            form: Markup,
+        ) -> Response {
+            match headers.contains_key("hx-request") {
+                true => (status, form).into_response(),
+                false => (status, login_page(context, form).await).into_response(),
+            }
         }
 
         pub async fn login_request(
-            State(state): State<AppState>,
+            context: ViewContext,
+            mut auth_session: AuthSession<Database>,
+            headers: HeaderMap,
             Form(payload): Form<Credential>,
-        ) -> (StatusCode, Markup) {
-            let maybe_user = User::from_email(payload.email, &state.pool).await;
-            let user = match maybe_user {
-                Err(_) => return (StatusCode::NOT_ACCEPTABLE, login_page().await),
-                Ok(user) => user,
+        ) -> Response {
+            let next = safe_next(&payload.next).to_string();
+            let errors = FormErrors::form(WRONG_CREDENTIALS);
+            let email = payload.email.clone();
+            let user = match auth_session.authenticate(payload).await {
+                Ok(Some(user)) => user,
+                Ok(None) | Err(_) => {
+                    let form = login_form(&next, &email, &errors);
+                    return User::login_errors(
+                        &context,
+                        &headers,
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        form,
+                    )
+                    .await;
+                }
             };
-            let valid = password_auth::verify_password(&payload.password, &user.pw_hash);
-            match valid {
-                Ok(_) => (StatusCode::OK, login_page().await),
-                Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, page_not_found()),
+            if user.is_suspended() {
+                return (StatusCode::FORBIDDEN, suspended_page(&context).await).into_response();
+            }
+            if auth_session.login(&user).await.is_err() {
+                let errors = FormErrors::form("We couldn't log you in, please try again");
+                let form = login_form(&next, &email, &errors);
+                return User::login_errors(
+                    &context,
+                    &headers,
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    form,
+                )
+                .await;
+            }
+            // htmx follows redirects itself and swaps the result in, so ask it to navigate instead
+            match headers.contains_key("hx-request") {
+                true => (StatusCode::OK, [("HX-Redirect", next)]).into_response(),
+                false => Redirect::to(&next).into_response(),
             }
         }
 
-        pub async fn user_list(State(state): State<AppState>) -> (StatusCode, Markup) {
+        pub async fn user_list(
+            RequireUser(_user): RequireUser,
+            State(state): State<AppState>,
+        ) -> (StatusCode, Markup) {
             let contents = maud::html! { ol {
                 @for user in User::get_all_users(&state.pool).await {
                     li { (user) }
@@ -281,36 +545,61 @@ mod control {
 mod view {
     use maud::{Markup, html};
 
-    use crate::views::utils::{default_header, title_and_navbar};
+    use crate::{
+        routes,
+        views::{
+            context::ViewContext,
+            form_errors::{FormErrors, field_error},
+            utils::{default_header, form_protection_fields, title_and_navbar},
+        },
+    };
+
+    use super::SignupUser;
 
-    pub async fn signup_page() -> Markup {
+    pub async fn signup_page(context: &ViewContext, form: Markup) -> Markup {
         html! {
             (default_header("Pallet Spaces: Signup"))
-            (title_and_navbar())
+            (title_and_navbar(context))
             body {
-                form id="signupForm" action="signup" method="POST" hx-post="/signup" {
-                    (email_form_html(true, ""))
-                    label for="Fullname" { "Fullname:" }
-                    input type="text" id="name" name="name" {}
-                    br {}
-                    label for="Password" { "Password:" }
-                    input type="text" id="password" name="password" {}
-                    br {}
-                    button type="submit" { "Submit" }
-                }
+                (form)
             }
         }
     }
 
-    pub fn email_form_html(valid: bool, email: &str) -> Markup {
-        let validation_class = match valid {
-            false => "invalid-form-input",
-            true => "valid-form-input",
+    /// The password is never filled back in.
+    pub fn signup_form(values: &SignupUser, errors: &FormErrors) -> Markup {
+        html! {
+            form id="signupForm" action=(routes::SIGNUP) method="POST" hx-post=(routes::SIGNUP) hx-target="this" hx-swap="outerHTML" {
+                (field_error(errors, FormErrors::FORM))
+                (email_form_html(&values.email, errors))
+                label for="Fullname" { "Fullname:" }
+                input type="text" id="name" name="name" value=(values.name) {}
+                (field_error(errors, "name"))
+                br {}
+                label for="Password" { "Password:" }
+                input type="text" id="password" name="password" {}
+                (field_error(errors, "password"))
+                br {}
+                label for="invite_code" { "Invite code:" }
+                input type="text" id="invite_code" name="invite_code" value=(values.invite_code) {}
+                (field_error(errors, "invite_code"))
+                br {}
+                (form_protection_fields())
+                button type="submit" { "Submit" }
+            }
+        }
+    }
+
+    pub fn email_form_html(email: &str, errors: &FormErrors) -> Markup {
+        let validation_class = match errors.get("email") {
+            Some(_) => "invalid-form-input",
+            None => "valid-form-input",
         };
         html! {
             div hx-target="this" hx-swap="outerHTML" {
                 label for="email" { "E-mail:" }
-                input type="text" id="email" name="email" class=(validation_class) hx-post="/signup/email" value=(email) { }
+                input type="text" id="email" name="email" class=(validation_class) hx-post=(routes::SIGNUP_EMAIL) value=(email) { }
+                (field_error(errors, "email"))
                 br {}
             }
         }
@@ -330,34 +619,39 @@ mod view {
         }
     }
 
-    pub async fn signup_failure() -> Markup {
+    pub async fn suspended_page(context: &ViewContext) -> Markup {
         html! {
-            (default_header("Pallet Spaces: Signup"))
+            (default_header("Pallet Spaces: Account suspended"))
+            (title_and_navbar(context))
             body {
                 h2 {
-                    "Attempted signup failed"
+                    "This account has been suspended"
                 }
                 p {
-                    "Please try again"
+                    "You won't be able to log in while we look into it. Contact "
+                    a href=(routes::SUPPORT) { "support" }
+                    " if you think this is a mistake."
                 }
             }
         }
     }
 
-    pub async fn login_page() -> Markup {
+    pub async fn login_page(context: &ViewContext, form: Markup) -> Markup {
         html! {
             (default_header("Pallet Spaces: Login"))
-            (title_and_navbar())
+            (title_and_navbar(context))
             body {
-                (login_form().await)
+                (form)
             }
         }
     }
 
-    pub async fn login_form() -> Markup {
+    pub fn login_form(next: &str, email: &str, errors: &FormErrors) -> Markup {
         html! {
-            form id="loginForm" action="login" method="POST" hx-post="/login" {
-                (email_form_html(true, ""))
+            form id="loginForm" action=(routes::LOGIN) method="POST" hx-post=(routes::LOGIN) hx-target="this" hx-swap="outerHTML" {
+                (field_error(errors, FormErrors::FORM))
+                input type="hidden" name="next" value=(next) {}
+                (email_form_html(email, errors))
                 label for="Password" { "Password:" }
                 input type="text" id="password" name="password" {}
                 br {}
@@ -365,4 +659,79 @@ mod view {
             }
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{
+            email_form_html, login_form, login_page, signup_form, signup_page, signup_success,
+            suspended_page,
+        };
+        use crate::{
+            plugins::users::SignupUser,
+            views::{context::ViewContext, form_errors::FormErrors, snapshot::normalise},
+        };
+
+        #[tokio::test]
+        async fn signup_page_form() {
+            let form = signup_form(&SignupUser::default(), &FormErrors::default());
+            insta::assert_snapshot!(normalise(signup_page(&ViewContext::default(), form).await));
+        }
+
+        #[test]
+        fn signup_form_keeps_values_with_errors() {
+            let values = SignupUser {
+                name: "Sam".to_string(),
+                email: "sam@example".to_string(),
+                password: "short".to_string(),
+                invite_code: "ABCDE12345".to_string(),
+                ..SignupUser::default()
+            };
+            let mut errors = values.validate();
+            errors.add(
+                "invite_code",
+                "That invite code isn't valid or has been used up",
+            );
+            insta::assert_snapshot!(normalise(signup_form(&values, &errors)));
+        }
+
+        #[test]
+        fn email_form_validation() {
+            insta::assert_snapshot!(
+                "email_form_valid",
+                normalise(email_form_html("", &FormErrors::default()))
+            );
+            let mut errors = FormErrors::default();
+            errors.add("email", "Enter a valid email address");
+            insta::assert_snapshot!(
+                "email_form_invalid",
+                normalise(email_form_html("not-an-email", &errors))
+            );
+        }
+
+        #[tokio::test]
+        async fn signup_results() {
+            insta::assert_snapshot!("signup_success", normalise(signup_success().await));
+        }
+
+        #[tokio::test]
+        async fn suspended_page_message() {
+            insta::assert_snapshot!(normalise(suspended_page(&ViewContext::default()).await));
+        }
+
+        #[tokio::test]
+        async fn login_page_keeps_next() {
+            let form = login_form("/new_post", "", &FormErrors::default());
+            insta::assert_snapshot!(normalise(login_page(&ViewContext::default(), form).await));
+        }
+
+        #[test]
+        fn login_form_wrong_credentials() {
+            let errors = FormErrors::form("Wrong email or password");
+            insta::assert_snapshot!(normalise(login_form(
+                "/new_post",
+                "sam@example.com",
+                &errors
+            )));
+        }
+    }
 }
@@ -0,0 +1,97 @@
+//! Optional error reporting, switched on by pointing `ERROR_WEBHOOK_URL` at anything
+//! that accepts a JSON POST (a Slack workflow, an incident tool, a small collector).
+use std::{sync::OnceLock, time::Duration};
+
+use serde::Serialize;
+
+/// Reports are best effort, a slow webhook shouldn't pile up background tasks.
+const REPORT_TIMEOUT: Duration = Duration::from_secs(5);
+
+static WEBHOOK_URL: OnceLock<Option<String>> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorReport {
+    pub request_id: String,
+    pub path: String,
+    pub version: &'static str,
+    pub detail: String,
+}
+
+impl ErrorReport {
+    pub fn new(request_id: &str, path: &str, detail: &str) -> Self {
+        ErrorReport {
+            request_id: request_id.to_string(),
+            path: path.to_string(),
+            version: env!("CARGO_PKG_VERSION"),
+            detail: detail.to_string(),
+        }
+    }
+}
+
+/// Reads `ERROR_WEBHOOK_URL` once, later calls return the same answer.
+pub fn webhook_url() -> Option<&'static str> {
+    WEBHOOK_URL
+        .get_or_init(|| std::env::var("ERROR_WEBHOOK_URL").ok())
+        .as_deref()
+}
+
+/// Sends `report` in the background when a webhook is configured, so the error page
+/// isn't held up. Failing to report is only logged.
+pub fn report(report: ErrorReport) {
+    let Some(url) = webhook_url() else {
+        return;
+    };
+    tokio::spawn(async move {
+        if let Err(err) = send_report(url, &report).await {
+            tracing::warn!("Couldn't report error {}: {}", report.request_id, err);
+        }
+    });
+}
+
+pub async fn send_report(url: &str, report: &ErrorReport) -> Result<(), reqwest::Error> {
+    reqwest::Client::new()
+        .post(url)
+        .timeout(REPORT_TIMEOUT)
+        .json(report)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{Json, Router, extract::State, routing::post};
+    use serde_json::Value;
+
+    use super::{ErrorReport, send_report};
+
+    type Received = Arc<Mutex<Vec<Value>>>;
+
+    async fn collect(State(received): State<Received>, Json(body): Json<Value>) {
+        received.lock().unwrap().push(body);
+    }
+
+    #[tokio::test]
+    async fn report_is_posted_as_json() {
+        let received = Received::default();
+        let collector = Router::new()
+            .route("/hook", post(collect))
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, collector).await });
+
+        let report = ErrorReport::new("req-1234", "/posts", "boom");
+        send_report(&url, &report).await.unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0]["request_id"], "req-1234");
+        assert_eq!(received[0]["path"], "/posts");
+        assert_eq!(received[0]["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(received[0]["detail"], "boom");
+    }
+}
@@ -0,0 +1,55 @@
+//! Every path the app serves. Route registration, links in views and redirects all
+//! go through here so renaming a route can't leave stale links behind.
+use crate::controller::auth::percent_encode;
+
+pub const HOME: &str = "/";
+pub const STATUS: &str = "/status.json";
+pub const PUBLIC: &str = "/public";
+
+pub const SIGNUP: &str = "/signup";
+pub const SIGNUP_EMAIL: &str = "/signup/email";
+pub const LOGIN: &str = "/login";
+pub const USERS: &str = "/users";
+
+pub const POSTS: &str = "/posts";
+pub const NEW_POST: &str = "/new_post";
+#[cfg(feature = "loadtest")]
+pub const LOADTEST_POSTS: &str = "/loadtest/posts";
+
+pub const SUPPORT: &str = "/support";
+
+/// Prefix for machine clients, errors under it are JSON rather than pages.
+pub const API: &str = "/api";
+
+/// Login page that sends the user on to `next` afterwards.
+pub fn login(next: &str) -> String {
+    format!("{}?next={}", LOGIN, percent_encode(next))
+}
+
+pub fn posts_search(query: &str) -> String {
+    format!("{}?q={}", POSTS, percent_encode(query))
+}
+
+/// A file under `frontend/public`, e.g. `public("js/htmx_2.0.4/htmx.min.js")`.
+pub fn public(asset: &str) -> String {
+    format!("{}/{}", PUBLIC, asset.trim_start_matches('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{login, posts_search, public};
+
+    #[test]
+    fn login_encodes_query() {
+        assert_eq!(
+            login("/posts?q=dock space&page=2"),
+            "/login?next=/posts%3Fq%3Ddock%20space%26page%3D2"
+        );
+    }
+
+    #[test]
+    fn search_and_assets() {
+        assert_eq!(posts_search("cold room"), "/posts?q=cold%20room");
+        assert_eq!(public("/js/app.js"), "/public/js/app.js");
+    }
+}
@@ -0,0 +1,118 @@
+use std::path::Path;
+
+use crate::{error::Error, model::database::Database};
+
+/// Checks run once at boot, before we start serving. Every problem found is
+/// reported together so a misconfigured deploy can be fixed in one go.
+pub async fn run(db: &Database, required_dirs: &[&str]) -> Result<(), Error> {
+    let mut problems = vec![];
+
+    if let Err(err) = check_database_writable(db).await {
+        problems.push(err);
+    }
+    for dir in required_dirs {
+        if !Path::new(dir).is_dir() {
+            problems.push(format!(
+                "Required directory {} is missing, run the server from the repo root",
+                dir
+            ));
+        }
+    }
+    if let Ok(base_url) = std::env::var("BASE_URL")
+        && let Err(err) = check_base_url(&base_url)
+    {
+        problems.push(err);
+    }
+
+    match problems.is_empty() {
+        true => Ok(()),
+        false => {
+            for problem in &problems {
+                tracing::error!("Startup check failed: {}", problem);
+            }
+            Err(Error::Config(problems.join("; ")))
+        }
+    }
+}
+
+async fn check_database_writable(db: &Database) -> Result<(), String> {
+    let fail = |err: sqlx::Error| {
+        format!(
+            "Database {} is not writable ({}), check permissions on the file and its directory",
+            db.path(),
+            err
+        )
+    };
+    let mut tx = db.writer().begin().await.map_err(fail)?;
+    sqlx::query("CREATE TABLE self_check (id INTEGER)")
+        .execute(&mut *tx)
+        .await
+        .map_err(fail)?;
+    tx.rollback().await.map_err(fail)
+}
+
+/// A trailing slash is fine, `UrlBuilder` trims it.
+fn check_base_url(base_url: &str) -> Result<(), String> {
+    let host = base_url
+        .strip_prefix("https://")
+        .or(base_url.strip_prefix("http://"))
+        .map(|host| host.trim_end_matches('/'));
+    match host {
+        Some(host) if !host.is_empty() => Ok(()),
+        _ => Err(format!(
+            "BASE_URL {:?} should look like https://example.com (scheme and host)",
+            base_url
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{check_base_url, check_database_writable, run};
+    use crate::{error::Error, model::database::Database};
+
+    #[test]
+    fn base_url_needs_scheme_and_host() {
+        assert!(check_base_url("https://pallets.example").is_ok());
+        assert!(check_base_url("http://localhost:3000").is_ok());
+        assert!(check_base_url("pallets.example").is_err());
+        assert!(check_base_url("https://").is_err());
+        assert!(check_base_url("https:///").is_err());
+    }
+
+    #[test]
+    fn base_url_may_end_with_slash() {
+        assert!(check_base_url("https://pallets.example/").is_ok());
+    }
+
+    #[tokio::test]
+    async fn reports_what_failed_and_where() {
+        let file = std::env::temp_dir().join(format!("pallet-spaces-{}.db", Uuid::new_v4()));
+        let db = Database::open(file.to_str().unwrap()).await.unwrap();
+
+        assert!(run(&db, &[env!("CARGO_MANIFEST_DIR")]).await.is_ok());
+        match run(&db, &["no-such-dir", "nor-this"]).await {
+            Err(Error::Config(message)) => {
+                assert!(message.contains("no-such-dir"));
+                assert!(message.contains("nor-this"));
+            }
+            _ => panic!("missing dirs should fail the check"),
+        }
+
+        // A leftover probe table makes the write check fail, the message should say where
+        sqlx::query("CREATE TABLE self_check (id INTEGER)")
+            .execute(db.writer())
+            .await
+            .unwrap();
+        let err = check_database_writable(&db).await.unwrap_err();
+        assert!(err.contains(file.to_str().unwrap()));
+
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = file.clone().into_os_string();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
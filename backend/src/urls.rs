@@ -0,0 +1,49 @@
+/// Builds absolute URLs from the configured `BASE_URL`, so anything that leaves the
+/// site (emails, payment return urls, sitemaps) links back to the right host.
+#[derive(Clone, Debug)]
+pub struct UrlBuilder {
+    base: String,
+}
+
+impl UrlBuilder {
+    pub fn new(base: &str) -> Self {
+        UrlBuilder {
+            base: base.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Reads `BASE_URL`, falling back to `default` (the local listen address).
+    pub fn from_env(default: &str) -> Self {
+        match std::env::var("BASE_URL") {
+            Ok(base) => UrlBuilder::new(&base),
+            Err(_) => UrlBuilder::new(default),
+        }
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    pub fn absolute(&self, path: &str) -> String {
+        format!("{}/{}", self.base, path.trim_start_matches('/'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UrlBuilder;
+
+    #[test]
+    fn joins_paths_with_single_slash() {
+        let urls = UrlBuilder::new("https://pallets.example");
+        assert_eq!(urls.absolute("/posts"), "https://pallets.example/posts");
+        assert_eq!(urls.absolute("posts"), "https://pallets.example/posts");
+    }
+
+    #[test]
+    fn trailing_slash_on_base_is_ignored() {
+        let urls = UrlBuilder::new("http://127.0.0.1:37373/");
+        assert_eq!(urls.base(), "http://127.0.0.1:37373");
+        assert_eq!(urls.absolute("/"), "http://127.0.0.1:37373/");
+    }
+}
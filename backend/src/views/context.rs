@@ -0,0 +1,97 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header::ACCEPT_LANGUAGE, request::Parts},
+};
+use axum_login::AuthSession;
+
+use crate::{devmode, model::database::Database, plugins::users::User};
+
+pub const DEFAULT_LOCALE: &str = "en";
+/// Until users and posts carry their own timezone.
+pub const DEFAULT_TIMEZONE: &str = "UTC";
+pub const DEFAULT_CURRENCY: &str = "AUD";
+
+/// Everything a page needs to know about who is looking at it. Built once per
+/// request (later extractions reuse it) and handed to every view that renders the
+/// layout, so new per-request flags get a field here rather than another argument.
+#[derive(Clone, Debug)]
+pub struct ViewContext {
+    pub user: Option<User>,
+    pub locale: String,
+    pub timezone: String,
+    pub currency: &'static str,
+    pub dev_mode: bool,
+}
+
+impl Default for ViewContext {
+    fn default() -> Self {
+        ViewContext {
+            user: None,
+            locale: DEFAULT_LOCALE.to_string(),
+            timezone: DEFAULT_TIMEZONE.to_string(),
+            currency: DEFAULT_CURRENCY,
+            dev_mode: false,
+        }
+    }
+}
+
+impl ViewContext {
+    /// Context for pages rendered outside the auth layer, e.g. the panic page.
+    pub fn anonymous() -> Self {
+        ViewContext {
+            dev_mode: devmode::is_enabled(),
+            ..ViewContext::default()
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ViewContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(context) = parts.extensions.get::<ViewContext>() {
+            return Ok(context.clone());
+        }
+        let user = match AuthSession::<Database>::from_request_parts(parts, state).await {
+            Ok(auth_session) => auth_session.user,
+            Err(_) => None,
+        };
+        let locale = parts
+            .headers
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(preferred_locale)
+            .unwrap_or(DEFAULT_LOCALE);
+        let context = ViewContext {
+            user,
+            locale: locale.to_string(),
+            ..ViewContext::anonymous()
+        };
+        parts.extensions.insert(context.clone());
+        Ok(context)
+    }
+}
+
+/// First language in an `Accept-Language` header, ignoring quality weights.
+fn preferred_locale(header: &str) -> Option<&str> {
+    let first = header.split(',').next()?.split(';').next()?.trim();
+    match first {
+        "" | "*" => None,
+        locale => Some(locale),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::preferred_locale;
+
+    #[test]
+    fn takes_first_accepted_language() {
+        assert_eq!(preferred_locale("en-AU,en;q=0.9"), Some("en-AU"));
+        assert_eq!(preferred_locale("fr;q=0.8, en"), Some("fr"));
+        assert_eq!(preferred_locale("*"), None);
+        assert_eq!(preferred_locale(""), None);
+    }
+}
@@ -0,0 +1,63 @@
+use maud::{Markup, html};
+
+/// Validation messages keyed by form field name, rendered inline by [`field_error`]
+/// so the form can be shown again with the user's input still in it.
+#[derive(Clone, Debug, Default)]
+pub struct FormErrors {
+    errors: Vec<(&'static str, String)>,
+}
+
+impl FormErrors {
+    /// Key for problems with the submission as a whole, shown above the fields.
+    pub const FORM: &'static str = "form";
+
+    pub fn add(&mut self, field: &'static str, message: &str) {
+        self.errors.push((field, message.to_string()));
+    }
+
+    /// Shorthand for a single form level error.
+    pub fn form(message: &str) -> Self {
+        let mut errors = FormErrors::default();
+        errors.add(FormErrors::FORM, message);
+        errors
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.errors
+            .iter()
+            .find(|(name, _)| *name == field)
+            .map(|(_, message)| message.as_str())
+    }
+}
+
+/// The message for `field`, or nothing if it's valid.
+pub fn field_error(errors: &FormErrors, field: &str) -> Markup {
+    html! {
+        @if let Some(message) = errors.get(field) {
+            p class="form-error" id=(format!("{}-error", field)) role="alert" { (message) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FormErrors, field_error};
+
+    #[test]
+    fn first_message_per_field_is_shown() {
+        let mut errors = FormErrors::default();
+        errors.add("email", "Enter an email address");
+        errors.add("email", "Already taken");
+        assert_eq!(errors.get("email"), Some("Enter an email address"));
+        assert_eq!(errors.get("name"), None);
+        assert_eq!(field_error(&errors, "name").into_string(), "");
+        assert_eq!(
+            field_error(&errors, "email").into_string(),
+            r#"<p class="form-error" id="email-error" role="alert">Enter an email address</p>"#
+        );
+    }
+}
@@ -1,14 +1,76 @@
-use crate::views::utils::title_and_navbar;
-use maud::{DOCTYPE, Markup, html};
+use crate::{
+    appstate::AppState,
+    plugins::posts::Post,
+    routes,
+    views::{context::ViewContext, utils::title_and_navbar},
+};
+use axum::extract::State;
+use maud::{Markup, html};
 
 use super::utils::default_header;
-pub async fn main_page() -> Markup {
+pub async fn main_page(context: ViewContext, State(state): State<AppState>) -> Markup {
+    let recent_posts = Post::recent_posts(6, &state.pool).await;
+    let space_count = Post::count_posts(&state.pool).await;
+    home_page(&context, &recent_posts, space_count)
+}
+
+pub fn home_page(context: &ViewContext, recent_posts: &[Post], space_count: i64) -> Markup {
     html! {
-        (DOCTYPE)
         (default_header("Pallet Spaces"))
-        (title_and_navbar())
+        (title_and_navbar(context))
         body {
-            p { "hello world" }
+            section {
+                h2 { "Warehouse space, one pallet at a time" }
+                p { "Find spare pallet spaces near you, or earn from the racking you aren't using." }
+                form action=(routes::POSTS) method="GET" {
+                    input type="search" name="q" placeholder="Where do you need space?" {}
+                    button type="submit" { "Search" }
+                }
+                p { "Spaces listed: " (space_count) }
+            }
+            section {
+                h3 { "Latest spaces" }
+                @if recent_posts.is_empty() {
+                    p { "Nothing listed yet. " a href=(routes::NEW_POST) { "List your space" } }
+                } @else {
+                    ul {
+                        @for post in recent_posts {
+                            li { (post.notes) }
+                        }
+                    }
+                    a href=(routes::POSTS) { "See all spaces" }
+                }
+            }
+            section {
+                h3 { "Why Pallet Spaces" }
+                ul {
+                    li { "Rent only the pallets you need, for as long as you need them." }
+                    li { "Hosts turn empty racking into income without long leases." }
+                }
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::home_page;
+    use crate::{
+        plugins::posts::Post,
+        views::{context::ViewContext, snapshot::normalise},
+    };
+
+    #[test]
+    fn home_page_empty() {
+        insta::assert_snapshot!(normalise(home_page(&ViewContext::default(), &[], 0)));
+    }
+
+    #[test]
+    fn home_page_with_posts() {
+        let posts = [
+            Post::new(&"Dry store in Dandenong, 12 pallets".to_string()),
+            Post::new(&"Cold room in Footscray, 4 pallets".to_string()),
+        ];
+        insta::assert_snapshot!(normalise(home_page(&ViewContext::default(), &posts, 2)));
+    }
+}
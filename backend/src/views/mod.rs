@@ -1,2 +1,6 @@
+pub mod context;
+pub mod form_errors;
 pub mod home;
+#[cfg(test)]
+pub mod snapshot;
 pub mod utils;
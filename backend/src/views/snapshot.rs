@@ -0,0 +1,27 @@
+//! Helpers for insta snapshot tests of views.
+use maud::Markup;
+
+/// Hidden fields whose values change on every render, with their placeholders.
+const VOLATILE_FIELDS: &[(&str, &str)] = &[
+    (r#"name="form_rendered_at" value=""#, "[timestamp]"),
+    (r#"name="submission_token" value=""#, "[token]"),
+];
+
+/// Puts each tag on its own line so snapshot diffs point at the changed element,
+/// and blanks out values that change between renders.
+pub fn normalise(markup: Markup) -> String {
+    let mut html = markup.into_string();
+    for (field, placeholder) in VOLATILE_FIELDS {
+        if let Some(start) = html.find(field) {
+            let value_start = start + field.len();
+            let value_end = value_start + html[value_start..].find('"').unwrap_or(0);
+            html = format!(
+                "{}{}{}",
+                &html[..value_start],
+                placeholder,
+                &html[value_end..]
+            );
+        }
+    }
+    html.replace("><", ">\n<")
+}
@@ -1,27 +1,127 @@
-use maud::{DOCTYPE, Markup, html};
+use maud::{DOCTYPE, Markup, PreEscaped, html};
+
+use crate::{
+    controller::form_protection::now_timestamp,
+    devmode::{DEV_EMAIL, DEV_PASSWORD},
+    routes,
+    views::context::ViewContext,
+};
+
+/// htmx 2 drops 4xx/5xx bodies by default, but our failure pages are meant to be
+/// shown, so swap them in. 401s aren't swapped, see [`htmx_login_redirect`].
+const HTMX_CONFIG: &str = r#"{"responseHandling":[{"code":"204","swap":false},{"code":"[23]..","swap":true},{"code":"401","swap":false,"error":true},{"code":"[45]..","swap":true,"error":true}]}"#;
+
+/// Sends the user to log in when an htmx request comes back 401, returning them here after.
+fn htmx_login_redirect() -> String {
+    format!(
+        r#"document.addEventListener("htmx:responseError", function (event) {{
+    if (event.detail.xhr.status === 401) {{
+        window.location.href = "{}" + encodeURIComponent(window.location.pathname + window.location.search);
+    }}
+}});"#,
+        routes::login("")
+    )
+}
 
 pub fn default_header(page_name: &str) -> Markup {
     html! {
         (DOCTYPE)
         head {
             title { (page_name.to_owned()) }
-            script src="/public/js/htmx_2.0.4/htmx.min.js" type="text/javascript" {}
+            meta name="htmx-config" content=(HTMX_CONFIG) {}
+            script src=(routes::public("js/htmx_2.0.4/htmx.min.js")) type="text/javascript" {}
+            script type="text/javascript" { (PreEscaped(htmx_login_redirect())) }
         }
     }
 }
 
-pub fn title_and_navbar() -> Markup {
+pub fn title_and_navbar(context: &ViewContext) -> Markup {
     html! {
+        @if context.dev_mode {
+            p class="dev-mode-banner" {
+                "Dev mode, log in as " code { (DEV_EMAIL) } " / " code { (DEV_PASSWORD) }
+            }
+        }
         h1 { "Pallet Spaces" }
         ul {
-            li { a href="/" { "Home" }}
-            li { a href="/signup" { "Signup" }}
+            li { a href=(routes::HOME) { "Home" }}
+            @match &context.user {
+                Some(user) => li { "Signed in as " (user.name) },
+                None => {
+                    li { a href=(routes::SIGNUP) { "Signup" }}
+                    li { a href=(routes::LOGIN) { "Login" }}
+                }
+            }
+            li { a href=(routes::SUPPORT) { "Support" }}
+        }
+    }
+}
+
+/// Honeypot and render-time fields checked by `FormProtection` on submit.
+pub fn form_protection_fields() -> Markup {
+    html! {
+        div style="position: absolute; left: -10000px;" aria-hidden="true" {
+            label for="website" { "Leave this empty:" }
+            input type="text" id="website" name="website" tabindex="-1" autocomplete="off" {}
+        }
+        input type="hidden" name="form_rendered_at" value=(now_timestamp()) {}
+    }
+}
+
+pub fn page_not_found(context: &ViewContext) -> Markup {
+    html! {
+        (default_header("Pallet Spaces: Not found"))
+        (title_and_navbar(context))
+        body {
+            h2 { "404: Page not found" }
+            p { "We couldn't find that page, it may have moved. Try searching for a space instead:" }
+            form action=(routes::POSTS) method="GET" {
+                input type="search" name="q" placeholder="Search spaces" {}
+                button type="submit" { "Search" }
+            }
         }
     }
 }
 
-pub fn page_not_found() -> Markup {
+pub fn server_error_page(context: &ViewContext, request_id: &str) -> Markup {
     html! {
-        h1 { "404: Page not found" }
+        (default_header("Pallet Spaces: Error"))
+        (title_and_navbar(context))
+        body {
+            h2 { "500: Something went wrong" }
+            p { "We've logged the problem, please try again in a moment." }
+            p { "If it keeps happening contact support with request id: " code { (request_id) } }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{page_not_found, server_error_page, title_and_navbar};
+    use crate::{
+        plugins::users::User,
+        views::{context::ViewContext, snapshot::normalise},
+    };
+
+    #[test]
+    fn not_found_page() {
+        insta::assert_snapshot!(normalise(page_not_found(&ViewContext::default())));
+    }
+
+    #[test]
+    fn server_error_page_shows_request_id() {
+        insta::assert_snapshot!(normalise(server_error_page(
+            &ViewContext::default(),
+            "5f0c6a1e-8d2b-4c7e-9f3a-2b1d0e4c6a8f"
+        )));
+    }
+
+    #[test]
+    fn navbar_for_signed_in_user() {
+        let context = ViewContext {
+            user: Some(User::new("Sam", "sam@example.com", "")),
+            ..ViewContext::default()
+        };
+        insta::assert_snapshot!(normalise(title_and_navbar(&context)));
     }
 }
@@ -0,0 +1,264 @@
+mod common;
+
+use axum::http::StatusCode;
+use axum_login::AuthUser;
+use common::{InviteFactory, TestApp, UserFactory};
+use pallet_spaces::plugins::{invites::Invite, users::User};
+
+#[tokio::test]
+async fn signup_then_login() {
+    let app = TestApp::new().await;
+    let signup = app
+        .submit_form(
+            "/signup",
+            &[
+                ("name", "Sam"),
+                ("email", "sam@example.com"),
+                ("password", "hunter22"),
+            ],
+        )
+        .await;
+    assert_eq!(signup.status, StatusCode::OK);
+
+    let login = app
+        .post_form(
+            "/login",
+            &[("email", "sam@example.com"), ("password", "hunter22")],
+        )
+        .await;
+    assert_eq!(login.status, StatusCode::SEE_OTHER);
+    assert_eq!(login.location(), Some("/"));
+    assert_eq!(app.get("/new_post").await.status, StatusCode::OK);
+    assert!(app.get("/").await.body.contains("Signed in as Sam"));
+}
+
+#[tokio::test]
+async fn signup_from_bot_is_rejected() {
+    let app = TestApp::new().await;
+    let signup = app
+        .post_form(
+            "/signup",
+            &[
+                ("name", "Bot"),
+                ("email", "bot@example.com"),
+                ("password", "x"),
+                ("website", "http://spam.example"),
+            ],
+        )
+        .await;
+    assert_eq!(signup.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn signup_needs_invite_when_required() {
+    let app = TestApp::with_invites(true).await;
+    let signup = app
+        .submit_form(
+            "/signup",
+            &[
+                ("name", "Sam"),
+                ("email", "sam@example.com"),
+                ("password", "hunter22"),
+            ],
+        )
+        .await;
+    assert_eq!(signup.status, StatusCode::FORBIDDEN);
+}
+
+async fn signup_with_invite(app: &TestApp, email: &str, code: &str) -> StatusCode {
+    app.submit_form(
+        "/signup",
+        &[
+            ("name", "Sam"),
+            ("email", email),
+            ("password", "hunter22"),
+            ("invite_code", code),
+        ],
+    )
+    .await
+    .status
+}
+
+#[tokio::test]
+async fn signup_redeems_invite() {
+    let app = TestApp::with_invites(true).await;
+    let code = InviteFactory::new().create(&app.db).await;
+    let status = signup_with_invite(&app, "sam@example.com", &code.to_lowercase()).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let invite = Invite::from_code(&code, &app.db).await.unwrap();
+    assert_eq!(invite.uses, 1);
+    let user = User::from_email("sam@example.com".to_string(), &app.db)
+        .await
+        .unwrap();
+    assert_eq!(
+        Invite::redeemed_by(&code, &app.db).await.unwrap(),
+        vec![user.id()]
+    );
+}
+
+#[tokio::test]
+async fn invite_stops_at_max_uses() {
+    let app = TestApp::with_invites(true).await;
+    let code = InviteFactory::new().max_uses(2).create(&app.db).await;
+    for email in ["one@example.com", "two@example.com"] {
+        assert_eq!(signup_with_invite(&app, email, &code).await, StatusCode::OK);
+    }
+    let status = signup_with_invite(&app, "three@example.com", &code).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let invite = Invite::from_code(&code, &app.db).await.unwrap();
+    assert_eq!(invite.uses, 2);
+    assert_eq!(Invite::redeemed_by(&code, &app.db).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn failed_signup_gives_invite_back() {
+    let app = TestApp::with_invites(true).await;
+    let code = InviteFactory::new().create(&app.db).await;
+    sqlx::query(
+        "CREATE TRIGGER reject_users BEFORE INSERT ON users BEGIN SELECT RAISE(ABORT, 'down'); END",
+    )
+    .execute(app.db.writer())
+    .await
+    .unwrap();
+    let status = signup_with_invite(&app, "sam@example.com", &code).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+    let invite = Invite::from_code(&code, &app.db).await.unwrap();
+    assert_eq!(invite.uses, 0);
+    assert!(
+        Invite::redeemed_by(&code, &app.db)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn login_returns_to_next() {
+    let app = TestApp::new().await;
+    let user = UserFactory::new().create(&app.db).await;
+    let login = app
+        .post_form(
+            "/login",
+            &[
+                ("email", &user.email),
+                ("password", &user.password),
+                ("next", "/new_post"),
+            ],
+        )
+        .await;
+    assert_eq!(login.location(), Some("/new_post"));
+}
+
+#[tokio::test]
+async fn login_ignores_offsite_next() {
+    let app = TestApp::new().await;
+    let user = UserFactory::new().create(&app.db).await;
+    let login = app
+        .post_form(
+            "/login",
+            &[
+                ("email", &user.email),
+                ("password", &user.password),
+                ("next", "//evil.example"),
+            ],
+        )
+        .await;
+    assert_eq!(login.location(), Some("/"));
+}
+
+#[tokio::test]
+async fn wrong_password_does_not_log_in() {
+    let app = TestApp::new().await;
+    let user = UserFactory::new().create(&app.db).await;
+    let login = app
+        .post_form("/login", &[("email", &user.email), ("password", "not it")])
+        .await;
+    assert_eq!(login.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(login.body.contains("Wrong email or password"));
+    assert!(login.body.contains(&user.email));
+    assert_eq!(app.get("/new_post").await.status, StatusCode::SEE_OTHER);
+}
+
+#[tokio::test]
+async fn suspended_user_cannot_log_in() {
+    let app = TestApp::new().await;
+    let user = UserFactory::new().suspended().create(&app.db).await;
+    assert_eq!(app.login(&user).await.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn suspending_a_user_ends_their_session() {
+    let app = TestApp::new().await;
+    let user = UserFactory::new().create(&app.db).await;
+    app.login(&user).await;
+    assert_eq!(app.get("/new_post").await.status, StatusCode::OK);
+
+    User::set_suspended(&user.email, true, &app.db)
+        .await
+        .unwrap();
+    let page = app.get("/new_post").await;
+    assert_eq!(page.status, StatusCode::SEE_OTHER);
+    assert_eq!(page.location(), Some("/login?next=/new_post"));
+}
+
+#[tokio::test]
+async fn anonymous_users_are_sent_to_login() {
+    let app = TestApp::new().await;
+    let page = app.get("/new_post").await;
+    assert_eq!(page.status, StatusCode::SEE_OTHER);
+    assert_eq!(page.location(), Some("/login?next=/new_post"));
+    assert_eq!(
+        app.hx_get("/new_post").await.status,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn signup_errors_are_shown_inline() {
+    let app = TestApp::new().await;
+    let signup = app
+        .submit_form(
+            "/signup",
+            &[("name", "Sam"), ("email", "sam"), ("password", "short")],
+        )
+        .await;
+    assert_eq!(signup.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(signup.body.contains("Enter a valid email address"));
+    assert!(signup.body.contains("Use at least 8 characters"));
+    assert!(signup.body.contains(r#"value="Sam""#));
+    assert!(!signup.body.contains("short"));
+}
+
+#[tokio::test]
+async fn signup_with_taken_email() {
+    let app = TestApp::new().await;
+    let user = UserFactory::new().create(&app.db).await;
+    let signup = app
+        .submit_form(
+            "/signup",
+            &[
+                ("name", "Sam"),
+                ("email", &user.email),
+                ("password", "hunter22"),
+            ],
+        )
+        .await;
+    assert_eq!(signup.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(signup.body.contains("already an account"));
+}
+
+#[tokio::test]
+async fn htmx_gets_just_the_form_back() {
+    let app = TestApp::new().await;
+    let login = app
+        .hx_post_form(
+            "/login",
+            &[("email", "nobody@example.com"), ("password", "x")],
+        )
+        .await;
+    assert_eq!(login.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(login.body.starts_with("<form"));
+}
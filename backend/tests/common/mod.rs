@@ -0,0 +1,241 @@
+//! Shared fixtures for the integration suites. Each `TestApp` gets its own database
+//! file so suites run in parallel without seeing each other's rows.
+#![allow(dead_code)]
+
+use std::{path::PathBuf, sync::Mutex};
+
+use axum::{
+    Router,
+    body::{Body, to_bytes},
+    http::{HeaderMap, Request, StatusCode, header},
+};
+use pallet_spaces::{
+    appstate::AppState,
+    controller::form_protection::now_timestamp,
+    create_router, initialise_tables,
+    model::database::{Database, DatabaseComponent},
+    plugins::{invites::Invite, posts::Post, users::User},
+    urls::UrlBuilder,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+pub struct TestApp {
+    pub db: Database,
+    router: Router,
+    file: PathBuf,
+    /// Session cookie from the last response that set one, sent with every request.
+    cookie: Mutex<Option<String>>,
+}
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+impl TestResponse {
+    pub fn location(&self) -> Option<&str> {
+        self.headers
+            .get(header::LOCATION)
+            .and_then(|value| value.to_str().ok())
+    }
+}
+
+impl TestApp {
+    pub async fn new() -> Self {
+        TestApp::with_invites(false).await
+    }
+
+    pub async fn with_invites(invite_required: bool) -> Self {
+        let file = std::env::temp_dir().join(format!("pallet-spaces-{}.db", Uuid::new_v4()));
+        let db = Database::open(file.to_str().unwrap()).await.unwrap();
+        let db = initialise_tables(db).await.unwrap();
+        let state = AppState::new(
+            db.clone(),
+            UrlBuilder::new("http://localhost"),
+            invite_required,
+        );
+        TestApp {
+            db,
+            router: create_router(state),
+            file,
+            cookie: Mutex::new(None),
+        }
+    }
+
+    pub async fn get(&self, path: &str) -> TestResponse {
+        self.send(Request::get(path), Body::empty()).await
+    }
+
+    /// Like `get`, but marked as an htmx request.
+    pub async fn hx_get(&self, path: &str) -> TestResponse {
+        self.send(
+            Request::get(path).header("hx-request", "true"),
+            Body::empty(),
+        )
+        .await
+    }
+
+    pub async fn post_form(&self, path: &str, fields: &[(&str, &str)]) -> TestResponse {
+        let body = serde_urlencoded::to_string(fields).unwrap();
+        let request =
+            Request::post(path).header(header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+        self.send(request, Body::from(body)).await
+    }
+
+    /// Like `post_form`, but marked as an htmx request.
+    pub async fn hx_post_form(&self, path: &str, fields: &[(&str, &str)]) -> TestResponse {
+        let body = serde_urlencoded::to_string(fields).unwrap();
+        let request = Request::post(path)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header("hx-request", "true");
+        self.send(request, Body::from(body)).await
+    }
+
+    /// Posts a form with the honeypot fields filled in like a person would.
+    pub async fn submit_form(&self, path: &str, fields: &[(&str, &str)]) -> TestResponse {
+        let rendered_at = (now_timestamp() - 10).to_string();
+        let mut fields = fields.to_vec();
+        fields.push(("website", ""));
+        fields.push(("form_rendered_at", &rendered_at));
+        self.post_form(path, &fields).await
+    }
+
+    pub async fn login(&self, user: &TestUser) -> TestResponse {
+        self.post_form(
+            "/login",
+            &[("email", &user.email), ("password", &user.password)],
+        )
+        .await
+    }
+
+    async fn send(&self, mut request: axum::http::request::Builder, body: Body) -> TestResponse {
+        if let Some(cookie) = self.cookie.lock().unwrap().as_ref() {
+            request = request.header(header::COOKIE, cookie);
+        }
+        let response = self
+            .router
+            .clone()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap();
+        if let Some(set_cookie) = response.headers().get(header::SET_COOKIE) {
+            let pair = set_cookie.to_str().unwrap().split(';').next().unwrap();
+            *self.cookie.lock().unwrap() = Some(pair.to_string());
+        }
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        TestResponse {
+            status,
+            headers,
+            body: String::from_utf8_lossy(&bytes).into_owned(),
+        }
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let mut file = self.file.clone().into_os_string();
+            file.push(suffix);
+            let _ = std::fs::remove_file(file);
+        }
+    }
+}
+
+pub struct TestUser {
+    pub name: String,
+    pub email: String,
+    pub password: String,
+}
+
+/// Builds users straight into the database, skipping the signup form.
+pub struct UserFactory {
+    user: TestUser,
+    suspended: bool,
+}
+
+impl UserFactory {
+    pub fn new() -> Self {
+        let id = Uuid::new_v4().simple().to_string();
+        UserFactory {
+            user: TestUser {
+                name: format!("Test {}", &id[..6]),
+                email: format!("{}@example.com", id),
+                password: "correct horse".to_string(),
+            },
+            suspended: false,
+        }
+    }
+
+    pub fn email(mut self, email: &str) -> Self {
+        self.user.email = email.to_string();
+        self
+    }
+
+    pub fn suspended(mut self) -> Self {
+        self.suspended = true;
+        self
+    }
+
+    pub async fn create(self, db: &Database) -> TestUser {
+        let pw_hash = password_auth::generate_hash(&self.user.password);
+        db.create(User::new(&self.user.name, &self.user.email, &pw_hash))
+            .await
+            .unwrap();
+        if self.suspended {
+            User::set_suspended(&self.user.email, true, db)
+                .await
+                .unwrap();
+        }
+        self.user
+    }
+}
+
+pub struct PostFactory {
+    notes: String,
+}
+
+impl PostFactory {
+    pub fn new() -> Self {
+        PostFactory {
+            notes: "Dry store in Dandenong, 12 pallets".to_string(),
+        }
+    }
+
+    pub fn notes(mut self, notes: &str) -> Self {
+        self.notes = notes.to_string();
+        self
+    }
+
+    pub async fn create(self, db: &Database) -> Post {
+        let post = Post::new(&self.notes);
+        db.create(post.clone()).await.unwrap();
+        post
+    }
+}
+
+pub struct InviteFactory {
+    max_uses: u32,
+}
+
+impl InviteFactory {
+    pub fn new() -> Self {
+        InviteFactory { max_uses: 1 }
+    }
+
+    pub fn max_uses(mut self, max_uses: u32) -> Self {
+        self.max_uses = max_uses;
+        self
+    }
+
+    /// Returns the invite code.
+    pub async fn create(self, db: &Database) -> String {
+        let invite = Invite::new(self.max_uses);
+        let code = invite.code.clone();
+        db.create(invite).await.unwrap();
+        code
+    }
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::{PostFactory, TestApp, UserFactory};
+use pallet_spaces::plugins::{posts::Post, users::User};
+
+#[tokio::test]
+async fn listing_and_search() {
+    let app = TestApp::new().await;
+    PostFactory::new()
+        .notes("Cold room in Footscray")
+        .create(&app.db)
+        .await;
+    PostFactory::new()
+        .notes("Dry store in Dandenong")
+        .create(&app.db)
+        .await;
+
+    let all = app.get("/posts").await;
+    assert_eq!(all.status, StatusCode::OK);
+    assert!(all.body.contains("Footscray") && all.body.contains("Dandenong"));
+
+    let search = app.get("/posts?q=cold").await;
+    assert!(search.body.contains("Footscray"));
+    assert!(!search.body.contains("Dandenong"));
+}
+
+#[tokio::test]
+async fn home_page_counts_posts() {
+    let app = TestApp::new().await;
+    PostFactory::new().create(&app.db).await;
+    let home = app.get("/").await;
+    assert!(home.body.contains("Spaces listed: 1"));
+}
+
+#[tokio::test]
+async fn suspended_hosts_listings_are_hidden() {
+    let app = TestApp::new().await;
+    PostFactory::new()
+        .notes("Dry store in Dandenong")
+        .create(&app.db)
+        .await;
+    let host = UserFactory::new().create(&app.db).await;
+    app.login(&host).await;
+    let fields = [
+        ("notes", "Cold room in Footscray"),
+        ("submission_token", "t1"),
+    ];
+    assert_eq!(
+        app.post_form("/new_post", &fields).await.status,
+        StatusCode::OK
+    );
+    assert!(app.get("/posts").await.body.contains("Footscray"));
+
+    User::set_suspended(&host.email, true, &app.db)
+        .await
+        .unwrap();
+    let all = app.get("/posts").await;
+    assert!(all.body.contains("Dandenong") && !all.body.contains("Footscray"));
+    assert!(
+        !app.get("/posts?q=footscray")
+            .await
+            .body
+            .contains("Footscray")
+    );
+    let home = app.get("/").await;
+    assert!(!home.body.contains("Footscray"));
+    assert!(home.body.contains("Spaces listed: 1"));
+
+    User::set_suspended(&host.email, false, &app.db)
+        .await
+        .unwrap();
+    assert!(app.get("/posts").await.body.contains("Footscray"));
+}
+
+#[tokio::test]
+async fn creating_a_post_needs_login() {
+    let app = TestApp::new().await;
+    let attempt = app.post_form("/new_post", &[("notes", "Mezzanine")]).await;
+    assert_eq!(attempt.status, StatusCode::SEE_OTHER);
+    assert_eq!(Post::count_posts(&app.db).await, 0);
+}
+
+/// Pulls the hidden submission token out of a rendered post form.
+fn submission_token(body: &str) -> String {
+    let marker = r#"name="submission_token" value=""#;
+    let start = body.find(marker).expect("form has a submission token") + marker.len();
+    let end = start + body[start..].find('"').unwrap();
+    body[start..end].to_string()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_double_submit_creates_one_post() {
+    let app = TestApp::new().await;
+    let user = UserFactory::new().create(&app.db).await;
+    app.login(&user).await;
+    let form = app.get("/new_post").await;
+    let token = submission_token(&form.body);
+    let fields = [
+        ("notes", "Mezzanine, 6 pallets"),
+        ("submission_token", &token),
+    ];
+
+    let (first, second) = tokio::join!(
+        app.post_form("/new_post", &fields),
+        app.post_form("/new_post", &fields),
+    );
+    assert_eq!(first.status, StatusCode::OK);
+    assert_eq!(second.status, StatusCode::OK);
+    assert_eq!(Post::count_posts(&app.db).await, 1);
+}
+
+#[tokio::test]
+async fn same_notes_from_another_user_are_kept() {
+    let app = TestApp::new().await;
+    let fields = [
+        ("notes", "Mezzanine, 6 pallets"),
+        ("submission_token", "shared"),
+    ];
+    for _ in 0..2 {
+        let user = UserFactory::new().create(&app.db).await;
+        app.login(&user).await;
+        let created = app.post_form("/new_post", &fields).await;
+        assert_eq!(created.status, StatusCode::OK);
+    }
+    assert_eq!(Post::count_posts(&app.db).await, 2);
+}
+
+#[tokio::test]
+async fn empty_post_is_rejected_inline() {
+    let app = TestApp::new().await;
+    let user = UserFactory::new().create(&app.db).await;
+    app.login(&user).await;
+    let created = app.post_form("/new_post", &[("notes", "  ")]).await;
+    assert_eq!(created.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(created.body.contains("Describe your space"));
+    assert_eq!(Post::count_posts(&app.db).await, 0);
+}
+
+#[cfg(feature = "loadtest")]
+#[tokio::test]
+async fn generated_posts_are_listed_and_searchable() {
+    let app = TestApp::new().await;
+    let generated = app.post_form("/loadtest/posts?count=50", &[]).await;
+    assert_eq!(generated.status, StatusCode::OK);
+    assert!(generated.body.contains(r#""created":50"#));
+    assert_eq!(Post::count_posts(&app.db).await, 50);
+
+    assert_eq!(Post::generate_posts(10, &app.db).await.unwrap(), 10);
+    assert_eq!(Post::count_posts(&app.db).await, 60);
+    let search = app.get("/posts?q=space%2049").await;
+    assert!(search.body.contains("Load test space 49"));
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum_login::AuthUser;
+use common::{TestApp, UserFactory};
+use pallet_spaces::plugins::users::User;
+use tracing::{
+    Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+};
+use tracing_subscriber::{Layer, Registry, layer::Context, prelude::*, registry::LookupSpan};
+
+/// Where a request span's `user_id` goes in [`CaptureUsers`].
+struct Slot(usize);
+
+struct UserIdVisitor<'a>(&'a mut Option<u64>);
+
+impl Visit for UserIdVisitor<'_> {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "user_id" {
+            *self.0 = Some(value);
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+}
+
+/// Collects the `user_id` of every `http.request` span, `None` until one is recorded.
+/// Spans are read as they're recorded rather than on close, the last handle can be
+/// dropped on another thread where this subscriber isn't the default.
+#[derive(Clone, Default)]
+struct CaptureUsers(Arc<Mutex<Vec<Option<u64>>>>);
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for CaptureUsers {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() == "http.request"
+            && let Some(span) = ctx.span(id)
+        {
+            let mut users = self.0.lock().unwrap();
+            users.push(None);
+            span.extensions_mut().insert(Slot(users.len() - 1));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id)
+            && let Some(Slot(slot)) = span.extensions().get::<Slot>()
+        {
+            values.record(&mut UserIdVisitor(&mut self.0.lock().unwrap()[*slot]));
+        }
+    }
+}
+
+impl CaptureUsers {
+    fn take(&self) -> Vec<Option<u64>> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+#[tokio::test]
+async fn request_span_records_the_logged_in_user() {
+    let capture = CaptureUsers::default();
+    let _guard = tracing::subscriber::set_default(Registry::default().with(capture.clone()));
+    let app = TestApp::new().await;
+
+    app.get("/").await;
+    assert_eq!(capture.take(), vec![None]);
+
+    let user = UserFactory::new().create(&app.db).await;
+    app.login(&user).await;
+    capture.take();
+    app.get("/new_post").await;
+    let id = User::from_email(user.email.clone(), &app.db)
+        .await
+        .unwrap()
+        .id();
+    assert_eq!(capture.take(), vec![Some(id as u64)]);
+}
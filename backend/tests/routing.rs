@@ -0,0 +1,83 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+
+#[tokio::test]
+async fn mixed_case_path_redirects_to_canonical() {
+    let app = TestApp::new().await;
+    let response = app.get("/Posts/").await;
+    assert_eq!(response.status, StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.location(), Some("/posts"));
+}
+
+#[tokio::test]
+async fn canonical_redirect_keeps_query() {
+    let app = TestApp::new().await;
+    let response = app.get("/POSTS?q=Cold%20Room").await;
+    assert_eq!(response.status, StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.location(), Some("/posts?q=Cold%20Room"));
+}
+
+#[tokio::test]
+async fn public_assets_are_not_redirected() {
+    let app = TestApp::new().await;
+    let response = app.get("/public/JS/App.js").await;
+    assert_ne!(response.status, StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.location(), None);
+}
+
+#[tokio::test]
+async fn home_is_left_alone() {
+    let app = TestApp::new().await;
+    let response = app.get("/").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.location(), None);
+}
+
+#[tokio::test]
+async fn unknown_page_gets_branded_404() {
+    let app = TestApp::new().await;
+    let response = app.get("/no-such-page").await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert!(response.body.contains("404: Page not found"));
+    assert!(response.body.contains(r#"name="q""#));
+}
+
+#[tokio::test]
+async fn unknown_api_path_gets_json_404() {
+    let app = TestApp::new().await;
+    let response = app.get("/api/no-such-thing").await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    let json: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(json["error"], "not found");
+    assert_eq!(json["path"], "/api/no-such-thing");
+}
+
+#[tokio::test]
+async fn search_works_before_any_posts_exist() {
+    let app = TestApp::new().await;
+    let response = app.get("/posts?q=cold%20room").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.body.contains("<ol></ol>"));
+}
+
+#[tokio::test]
+async fn leading_slashes_never_redirect_off_site() {
+    let app = TestApp::new().await;
+    for path in [
+        "//evil.example/",
+        "//evil.example",
+        "///Evil.example/path",
+        "/\\evil.example/",
+    ] {
+        let response = app.get(path).await;
+        if let Some(location) = response.location() {
+            assert!(location.starts_with('/'), "{} -> {}", path, location);
+            assert!(!location.starts_with("//"), "{} -> {}", path, location);
+            assert!(!location.starts_with("/\\"), "{} -> {}", path, location);
+        }
+    }
+    let response = app.get("//evil.example/").await;
+    assert_eq!(response.location(), Some("/evil.example"));
+}
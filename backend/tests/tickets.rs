@@ -0,0 +1,36 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::TestApp;
+
+#[tokio::test]
+async fn support_ticket_is_accepted() {
+    let app = TestApp::new().await;
+    let ticket = app
+        .submit_form(
+            "/support",
+            &[
+                ("category", "Listing"),
+                ("email", "sam@example.com"),
+                ("message", "How do I add photos?"),
+            ],
+        )
+        .await;
+    assert_eq!(ticket.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn unknown_category_is_rejected() {
+    let app = TestApp::new().await;
+    let ticket = app
+        .submit_form(
+            "/support",
+            &[
+                ("category", "Refunds please"),
+                ("email", "sam@example.com"),
+                ("message", "Hello"),
+            ],
+        )
+        .await;
+    assert_eq!(ticket.status, StatusCode::BAD_REQUEST);
+}